@@ -0,0 +1,156 @@
+// benches/window.rs
+// 滑动窗口吞吐量：在进程内启动参考服务端，通过带延迟的 unix socket 以不同窗口大小保存同一份报告
+//
+// 本地 socket 没有往返延迟，窗口大小几乎不影响耗时；这里用 `Delayed` 给客户端发出的数据加上固定延迟，
+// 并把消息包固定为 1 KiB 以产生足够多的分片，测出的差异才来自窗口大小。
+// `Client` 按地址自行建立连接，无法替换传输层，所以直接用 `Multiplexer` 在包装后的连接上每次保存一份报告，
+// 与 `Client` 使用同一个窗口发送器。
+//
+// 运行: cargo bench --bench window
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use xbox_client::constants;
+use xbox_client::mux::Multiplexer;
+use xbox_client::protocol::FramedStream;
+use xbox_client::server::{Server, Storage};
+use xbox_client::{CodecId, Endpoint, Transport};
+
+// 报告大小、每个窗口大小的保存次数、单向延迟和消息包长度
+const PAYLOAD_SIZE: usize = 512 * 1024;
+const ITERATIONS: u32 = 3;
+const LATENCY: Duration = Duration::from_millis(1);
+const MAX_PACKET_SIZE: u32 = 1024;
+const WINDOW_SIZES: [u32; 5] = [1, 4, 16, 32, 64];
+
+/// 写出的数据延迟 `LATENCY` 后才到达对端的连接
+///
+/// 写入的数据连同到达时间交给后台线程，由它按时间顺序转发，多次写入可以同时在途；
+/// 关闭写方向同样排队，保证之前写入的数据先送达。
+struct Delayed {
+    stream: UnixStream,
+    queue: Sender<(Instant, Option<Vec<u8>>)>,
+}
+
+impl Delayed {
+    fn new(stream: UnixStream) -> io::Result<Self> {
+        let mut writer = stream.try_clone()?;
+        let (queue, pending) = mpsc::channel::<(Instant, Option<Vec<u8>>)>();
+        thread::spawn(move || {
+            for (due, data) in pending {
+                thread::sleep(due.saturating_duration_since(Instant::now()));
+                let result = match data {
+                    Some(data) => writer.write_all(&data),
+                    None => writer.shutdown(Shutdown::Write),
+                };
+                if result.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Self { stream, queue })
+    }
+
+    fn send(&self, data: Option<Vec<u8>>) -> io::Result<()> {
+        self.queue
+            .send((Instant::now() + LATENCY, data))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "延迟转发线程已退出"))
+    }
+}
+
+impl Read for Delayed {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.read(buf)
+    }
+}
+
+impl Write for Delayed {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.send(Some(buf.to_vec()))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for Delayed {
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        if how != Shutdown::Write {
+            self.stream.shutdown(Shutdown::Read)?;
+        }
+        if how != Shutdown::Read {
+            self.send(None)?;
+        }
+        Ok(())
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.set_read_timeout(timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.set_write_timeout(timeout)
+    }
+}
+
+fn main() {
+    let dir = std::env::temp_dir().join(format!("xbox-bench-{}", std::process::id()));
+    let socket = dir.join("server.sock");
+    let storage = Storage::open(dir.join("storage")).expect("创建存储目录失败");
+    let server = Server::bind(Endpoint::Unix(socket.clone()), storage).expect("启动服务端失败");
+    thread::spawn(move || server.run());
+    thread::sleep(Duration::from_millis(100));
+
+    let report = payload();
+    let mut results = Vec::new();
+    for window in WINDOW_SIZES {
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            save(&socket, window, report.as_bytes());
+        }
+        results.push((window, start.elapsed()));
+    }
+
+    let baseline = results[0].1.as_secs_f64();
+    println!();
+    println!("单向延迟 {:?}，消息包 {} 字节", LATENCY, MAX_PACKET_SIZE);
+    println!("{:>6} {:>12} {:>12} {:>8}", "窗口", "耗时", "MiB/s", "加速比");
+    for (window, elapsed) in results {
+        let secs = elapsed.as_secs_f64();
+        let mib = (PAYLOAD_SIZE as f64 * ITERATIONS as f64) / (1024.0 * 1024.0);
+        println!("{:>6} {:>12.3?} {:>12.1} {:>8.2}", window, elapsed, mib / secs, baseline / secs);
+    }
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// 在新连接上以 `window` 保存一份不压缩的报告，只比较传输本身
+fn save(socket: &Path, window: u32, report: &[u8]) {
+    let stream = UnixStream::connect(socket).expect("连接服务端失败");
+    let stream = FramedStream::new(Delayed::new(stream).expect("创建延迟连接失败"));
+    let mut mux = Multiplexer::new(stream, window, MAX_PACKET_SIZE);
+    mux.start(constants::SAVE_COMMAND, CodecId::None, report).expect("发送 START 失败");
+    for (_, result) in mux.finish().expect("连接失败") {
+        result.expect("保存失败");
+    }
+}
+
+/// 不可压缩的 JSON 报告
+fn payload() -> String {
+    let mut blob = String::with_capacity(PAYLOAD_SIZE);
+    let mut x: u64 = 0x9e37_79b9_7f4a_7c15;
+    while blob.len() < PAYLOAD_SIZE - 32 {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        let _ = write!(blob, "{:016x}", x);
+    }
+    format!("{{\"servers\":[],\"blob\":\"{}\"}}", blob)
+}
@@ -0,0 +1,327 @@
+// src/bin/xbox-client.rs
+// 黑匣子命令行客户端
+//
+// 用法: xbox-client [选项] <命令> [参数]
+//   save <file.json>               读取 JSON 文件并保存为系统报告（也可写作 --save）
+//   dump [-o <out.json>]           取回全部系统报告，默认输出到标准输出（也可写作 --dump）
+//   save-process <file.json>       读取 JSON 文件并保存为进程报告（也可写作 --save-process）
+//   dump-process [-o <out.json>]   取回全部进程报告，默认输出到标准输出（也可写作 --dump-process）
+//   validate <file.json>           按内置 schema 校验进程报告，不连接服务端
+//   daemon                         常驻采样并定期保存系统报告，SIGHUP 重新加载配置
+//   spool <list|flush|purge>       查看 / 立即补发 / 清空 spool 中保存失败的报告
+//
+// 选项:
+//   --cid <cid>           服务端 CID
+//   --port <port>         服务端端口
+//   --endpoint <地址>     服务端地址，如 vsock://3:1234、unix:///path/to/sock、tcp://127.0.0.1:1234
+//   --timeout <秒>        连接、握手、ACK 超时（0 表示不限时）
+//   --format <json|pretty> dump 输出格式，默认 json
+//   --codec <编码>        负载编码 zlib / none / gzip / zstd / lz4，服务端不支持时自动回退
+//   --spool-dir <目录>    启用 spool，服务端不可达时报告写入该目录，之后补发
+//   --stream              save / dump 使用流式传输，适合很大的报告（不校验、不经过 spool、忽略 --format）
+//
+// 未通过选项指定的配置取自 XBOX_CONFIG 指定的文件和 XBOX_* 环境变量。
+//
+// 退出码: 0 成功, 2 参数错误, 3 配置错误, 4 连接失败, 5 超时,
+//         6 服务端拒绝或协议错误, 7 数据错误, 1 其他错误
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process::ExitCode;
+use std::time::Duration;
+use xbox_client::{constants, daemon, data_process, utils};
+use xbox_client::{Client, ClientConfig, ClientError, CodecId, Endpoint};
+
+const USAGE: &str = "用法: xbox-client [--cid <cid>] [--port <port>] [--endpoint <地址>] [--timeout <秒>] [--format <json|pretty>] [--codec <编码>] [--stream]
+                   save <file.json> | dump [-o <out.json>]
+                   save-process <file.json> | dump-process [-o <out.json>]
+                   validate <file.json> | daemon | spool <list|flush|purge>";
+
+/// dump 输出格式
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Json,
+    Pretty,
+}
+
+/// 子命令
+#[derive(Clone, Copy, PartialEq)]
+enum Command {
+    /// 与服务端交互的命令，值为 constants 中的命令编号
+    Remote(u8),
+    /// 本地校验进程报告
+    Validate,
+    /// 常驻采样
+    Daemon,
+    /// 管理 spool，参数为 list / flush / purge
+    Spool,
+}
+
+/// 解析后的命令行参数
+struct Args {
+    command: Option<Command>,
+    input: Option<String>,
+    output: Option<String>,
+    cid: Option<u32>,
+    port: Option<u32>,
+    endpoint: Option<String>,
+    timeout: Option<u64>,
+    spool_dir: Option<String>,
+    codec: Option<CodecId>,
+    stream: bool,
+    format: Format,
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(msg) => {
+            eprintln!("{}", msg);
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("[xbox-client] ✗ {}", e);
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+/// 解析参数，返回 None 表示只需打印帮助
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args {
+        command: None,
+        input: None,
+        output: None,
+        cid: None,
+        port: None,
+        endpoint: None,
+        timeout: None,
+        spool_dir: None,
+        codec: None,
+        stream: false,
+        format: Format::Json,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--cid" => parsed.cid = Some(parse_number(&arg, args.next())?),
+            "--port" => parsed.port = Some(parse_number(&arg, args.next())?),
+            "--timeout" => parsed.timeout = Some(parse_number(&arg, args.next())?),
+            "--endpoint" => parsed.endpoint = Some(required(&arg, args.next())?),
+            "--stream" => parsed.stream = true,
+            "--codec" => {
+                let name = required(&arg, args.next())?;
+                parsed.codec = Some(name.parse().map_err(|_| format!("未知编码: {} (可选 zlib/none/gzip/zstd/lz4)", name))?);
+            }
+            "--spool-dir" => parsed.spool_dir = Some(required(&arg, args.next())?),
+            "-o" | "--output" => parsed.output = Some(required(&arg, args.next())?),
+            "--format" => {
+                parsed.format = match required(&arg, args.next())?.as_str() {
+                    "json" => Format::Json,
+                    "pretty" => Format::Pretty,
+                    other => return Err(format!("未知输出格式: {}", other)),
+                }
+            }
+            "validate" | "--validate" if parsed.command.is_none() => parsed.command = Some(Command::Validate),
+            "daemon" | "--daemon" if parsed.command.is_none() => parsed.command = Some(Command::Daemon),
+            "spool" | "--spool" if parsed.command.is_none() => parsed.command = Some(Command::Spool),
+            _ if parsed.command.is_none() => {
+                // 子命令与 constants 中的 --xxx 形式等价
+                let name = if arg.starts_with("--") { arg.clone() } else { format!("--{}", arg) };
+                match utils::get_command_code(&name) {
+                    0 => return Err(format!("未知参数: {}", arg)),
+                    code => parsed.command = Some(Command::Remote(code)),
+                }
+            }
+            _ if parsed.input.is_none() && !arg.starts_with('-') => parsed.input = Some(arg),
+            _ => return Err(format!("未知参数: {}", arg)),
+        }
+    }
+
+    match parsed.command {
+        None => Err("缺少命令".to_string()),
+        Some(Command::Remote(constants::SAVE_COMMAND | constants::SAVE_PROCESS_COMMAND) | Command::Validate)
+            if parsed.input.is_none() => Err("缺少 JSON 文件路径".to_string()),
+        Some(Command::Spool) if !matches!(parsed.input.as_deref(), Some("list" | "flush" | "purge")) => {
+            Err("spool 需要 list / flush / purge 之一".to_string())
+        }
+        _ => Ok(Some(parsed)),
+    }
+}
+
+fn required(flag: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("{} 缺少参数值", flag))
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = required(flag, value)?;
+    value.parse().map_err(|_| format!("{} 的值无效: {}", flag, value))
+}
+
+/// 命令行选项覆盖配置文件和环境变量
+fn build_config(args: &Args) -> xbox_client::Result<ClientConfig> {
+    let mut builder = ClientConfig::env_builder()?;
+    if let Some(endpoint) = &args.endpoint {
+        builder = builder.endpoint(endpoint.parse::<Endpoint>()?);
+    }
+    if let Some(cid) = args.cid {
+        builder = builder.cid(cid);
+    }
+    if let Some(port) = args.port {
+        builder = builder.port(port);
+    }
+    if let Some(dir) = &args.spool_dir {
+        builder = builder.spool_dir(dir);
+    }
+    if let Some(codec) = args.codec {
+        builder = builder.codec(codec);
+    }
+    if let Some(secs) = args.timeout {
+        let timeout = (secs > 0).then(|| Duration::from_secs(secs));
+        builder = builder
+            .connect_timeout(timeout)
+            .handshake_timeout(timeout)
+            .ack_timeout(timeout);
+    }
+    builder.build()
+}
+
+fn run(args: &Args) -> xbox_client::Result<()> {
+    let command = match args.command {
+        Some(Command::Remote(code)) => code,
+        Some(Command::Daemon) => return daemon::run(|| build_config(args)),
+        Some(Command::Spool) => return spool(args),
+        _ => return validate(args),
+    };
+    let client = Client::new(build_config(args)?);
+
+    if args.stream {
+        return stream(&client, command, args);
+    }
+
+    // get_command_code 只会返回四种命令之一
+    match command {
+        constants::SAVE_PROCESS_COMMAND => client.send_process(read_input(args)?),
+        constants::SAVE_COMMAND => client.send(command, read_input(args)?),
+        command => {
+            let bytes = client.dump(command)?;
+            write_dump(&bytes, args)
+        }
+    }
+}
+
+fn read_input(args: &Args) -> xbox_client::Result<String> {
+    let path = args.input.as_deref().unwrap_or_default();
+    data_process::read_json_compact(path)
+        .map_err(|e| ClientError::Io(io::Error::new(io::ErrorKind::InvalidData, e.to_string())))
+}
+
+/// 校验进程报告，逐条打印错误
+fn validate(args: &Args) -> xbox_client::Result<()> {
+    let path = args.input.as_deref().unwrap_or_default();
+    let json = fs::read_to_string(path).map_err(ClientError::Io)?;
+    let errors = data_process::validate_process_report(&json);
+    if errors.is_empty() {
+        println!("{}: 校验通过", path);
+        return Ok(());
+    }
+    for e in &errors {
+        println!("{}: {}", path, e);
+    }
+    Err(ClientError::Validation(format!("{} 共 {} 处错误", path, errors.len())))
+}
+
+/// 流式 save / dump，文件内容不整体读入内存
+fn stream(client: &Client, command: u8, args: &Args) -> xbox_client::Result<()> {
+    match command {
+        constants::SAVE_COMMAND | constants::SAVE_PROCESS_COMMAND => {
+            let file = fs::File::open(args.input.as_deref().unwrap_or_default()).map_err(ClientError::Io)?;
+            client.save_from_reader(command, io::BufReader::new(file)).map(|_| ())
+        }
+        _ => match &args.output {
+            Some(path) if path != "-" => {
+                let file = fs::File::create(path).map_err(ClientError::Io)?;
+                client.dump_to_writer(command, io::BufWriter::new(file)).map(|_| ())
+            }
+            _ => client.dump_to_writer(command, io::stdout().lock()).map(|_| ()),
+        },
+    }
+}
+
+/// spool list / flush / purge
+fn spool(args: &Args) -> xbox_client::Result<()> {
+    let client = Client::new(build_config(args)?);
+    let Some(spool) = client.spool() else {
+        return Err(ClientError::Config("未配置 spool 目录 (--spool-dir 或 XBOX_SPOOL_DIR)".to_string()));
+    };
+
+    match args.input.as_deref() {
+        Some("list") => {
+            let records = spool.list()?;
+            for record in &records {
+                let created = chrono::DateTime::from_timestamp_millis(record.created_ms as i64)
+                    .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default();
+                println!("{:>8}  {}  {:#04x}  {} 字节", record.seq, created, record.command, record.payload.len());
+            }
+            println!("共 {} 条待补发记录 ({})", records.len(), spool.dir().display());
+            Ok(())
+        }
+        Some("flush") => {
+            let sent = client.flush_spool()?;
+            println!("已补发 {} 条记录", sent);
+            Ok(())
+        }
+        _ => {
+            let purged = spool.purge()?;
+            println!("已删除 {} 条待补发记录", purged);
+            Ok(())
+        }
+    }
+}
+
+/// 按指定格式输出 dump 结果，没有报告时输出空数组
+fn write_dump(bytes: &[u8], args: &Args) -> xbox_client::Result<()> {
+    let reports: serde_json::Value = if bytes.is_empty() {
+        serde_json::Value::Array(Vec::new())
+    } else {
+        serde_json::from_slice(bytes)?
+    };
+    let mut text = match args.format {
+        Format::Json => serde_json::to_string(&reports)?,
+        Format::Pretty => serde_json::to_string_pretty(&reports)?,
+    };
+    text.push('\n');
+
+    match &args.output {
+        Some(path) if path != "-" => fs::write(path, text).map_err(ClientError::Io),
+        _ => io::stdout().write_all(text.as_bytes()).map_err(ClientError::Io),
+    }
+}
+
+/// 按错误类型映射退出码，便于脚本区分失败原因
+fn exit_code(e: &ClientError) -> u8 {
+    match e {
+        ClientError::Config(_) => 3,
+        ClientError::Connect(_) => 4,
+        ClientError::Timeout(_) => 5,
+        ClientError::Handshake(_)
+        | ClientError::Nack(_)
+        | ClientError::Remote(_)
+        | ClientError::CodecRejected { .. }
+        | ClientError::PacketSizeRejected { .. }
+        | ClientError::ProtocolViolation(_) => 6,
+        ClientError::Checksum(_) | ClientError::Decompress(_) | ClientError::Json(_) | ClientError::Validation(_) => 7,
+        ClientError::Io(_) => 1,
+    }
+}
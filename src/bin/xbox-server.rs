@@ -0,0 +1,72 @@
+// src/bin/xbox-server.rs
+// 黑匣子参考服务端
+//
+// 用法: xbox-server [--listen <地址>] [--storage <目录>] [--max-report-size <字节>]
+//   --listen           监听地址，默认 vsock://4294967295:1234（任意 CID）
+//                      也可以是 unix:///path/to/sock 或 tcp://127.0.0.1:1234
+//   --storage          报告存储目录，默认 ./xbox-storage
+//   --max-report-size  单份报告（压缩后）的大小上限，默认 256 MiB
+use std::env;
+use std::process::ExitCode;
+use xbox_client::server::{Server, Storage, DEFAULT_MAX_REPORT_SIZE};
+use xbox_client::Endpoint;
+
+const DEFAULT_LISTEN: &str = "vsock://4294967295:1234";
+const DEFAULT_STORAGE: &str = "./xbox-storage";
+
+fn main() -> ExitCode {
+    let mut listen = DEFAULT_LISTEN.to_string();
+    let mut storage_dir = DEFAULT_STORAGE.to_string();
+    let mut max_report_size = DEFAULT_MAX_REPORT_SIZE;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match arg.as_str() {
+            "-h" | "--help" => {
+                println!("用法: xbox-server [--listen <地址>] [--storage <目录>] [--max-report-size <字节>]");
+                return ExitCode::SUCCESS;
+            }
+            "--listen" | "--storage" | "--max-report-size" => match args.next() {
+                Some(v) => v,
+                None => {
+                    eprintln!("{} 缺少参数值", arg);
+                    return ExitCode::from(2);
+                }
+            },
+            _ => {
+                eprintln!("未知参数: {}", arg);
+                return ExitCode::from(2);
+            }
+        };
+        match arg.as_str() {
+            "--listen" => listen = value,
+            "--storage" => storage_dir = value,
+            _ => match value.parse() {
+                Ok(size) => max_report_size = size,
+                Err(_) => {
+                    eprintln!("{} 的值无效: {}", arg, value);
+                    return ExitCode::from(2);
+                }
+            },
+        }
+    }
+
+    let result = listen
+        .parse::<Endpoint>()
+        .map_err(anyhow::Error::from)
+        .and_then(|endpoint| {
+            let storage = Storage::open(&storage_dir)?;
+            let mut server = Server::bind(endpoint, storage)?;
+            server.set_max_report_size(max_report_size);
+            Ok(server)
+        })
+        .and_then(|server| server.run());
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("[Server] ✗ {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,209 @@
+// src/client.rs
+// 基于 ClientConfig 的客户端，封装 save / dump 流程
+use std::io::{Read, Write};
+use crate::codec::CodecId;
+use crate::config::ClientConfig;
+use crate::constants;
+use crate::error::{ClientError, Result};
+use crate::model::Report;
+use crate::mux::Multiplexer;
+use crate::spool::Spool;
+use crate::{client_thread_dump, client_thread_save, data_process, utils, VSOCK_MUTEX};
+
+/// 黑匣子客户端
+pub struct Client {
+    config: ClientConfig,
+    spool: Option<Spool>,
+}
+
+impl Client {
+    /// 配置了 spool 目录时同时打开 spool；打开失败时不启用 spool
+    pub fn new(config: ClientConfig) -> Self {
+        let spool = config.spool.dir.as_ref().and_then(|dir| {
+            Spool::open(dir, config.spool.max_bytes, config.spool.max_age)
+                .map_err(|e| eprintln!("[Spool] ✗ 打开失败，本次不启用 spool: {}", e))
+                .ok()
+        });
+        Self { config, spool }
+    }
+
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    pub fn spool(&self) -> Option<&Spool> {
+        self.spool.as_ref()
+    }
+
+    /// 压缩并保存一份进程报告
+    pub fn send_process(&self, message_str: String) -> Result<()> {
+        data_process::check_process_report(&message_str, self.config.validation)?;
+        self.send(constants::SAVE_PROCESS_COMMAND, message_str)
+    }
+
+    /// 取回服务端保存的全部进程报告，返回 JSON 数组的字节
+    pub fn dump_process(&self) -> Result<Vec<u8>> {
+        self.dump(constants::DUMP_PROCESS_COMMAND)
+    }
+
+    /// 压缩并保存一份系统报告（systemMetrics / crashLogs 等系统级数据）
+    pub fn send_system(&self, message_str: String) -> Result<()> {
+        self.send(constants::SAVE_COMMAND, message_str)
+    }
+
+    /// 取回服务端保存的全部系统报告，返回 JSON 数组的字节
+    pub fn dump_system(&self) -> Result<Vec<u8>> {
+        self.dump(constants::DUMP_COMMAND)
+    }
+
+    /// 序列化并保存一份结构化报告（系统报告）
+    pub fn save_report(&self, report: &Report) -> Result<()> {
+        self.send_system(serde_json::to_string(report)?)
+    }
+
+    /// 取回全部系统报告并解析为结构化报告
+    pub fn dump_reports(&self) -> Result<Vec<Report>> {
+        let bytes = self.dump_system()?;
+        // 没有报告时服务端不返回任何数据
+        if bytes.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    /// 使用指定的保存命令发送一份报告
+    ///
+    /// 启用 spool 时，服务端不可达（连接失败、超时、连接中断）的报告写入 spool 后返回成功，
+    /// 由 `flush_spool` 补发；spool 中已有待补发的记录时新报告直接排在其后，保证顺序。
+    pub fn send(&self, command: u8, message_str: String) -> Result<()> {
+        let Some(spool) = &self.spool else {
+            return self.send_direct(command, &message_str);
+        };
+
+        if !spool.is_empty()? {
+            let seq = spool.push(command, message_str.as_bytes())?;
+            println!("[Spool] 已有待补发的记录，报告排队 seq={}", seq);
+            if let Err(e) = self.flush_spool() {
+                eprintln!("[Spool] ✗ 补发失败，稍后重试: {}", e);
+            }
+            return Ok(());
+        }
+
+        match self.send_direct(command, &message_str) {
+            Err(e) if e.is_retryable() => {
+                let seq = spool.push(command, message_str.as_bytes())?;
+                eprintln!("[Spool] ✗ 发送失败，报告已写入 spool seq={}: {}", seq, e);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// 按顺序补发 spool 中的记录，返回成功补发的条数；未启用 spool 时返回 0
+    ///
+    /// 被服务端拒绝的记录（非重试类错误）丢弃，避免阻塞之后的记录。
+    pub fn flush_spool(&self) -> Result<usize> {
+        let Some(spool) = &self.spool else { return Ok(0) };
+        spool.flush(|record| {
+            let message = String::from_utf8_lossy(&record.payload);
+            match self.send_direct(record.command, &message) {
+                Err(e) if !e.is_retryable() => {
+                    eprintln!("[Spool] ✗ 记录 seq={} 被拒绝，已丢弃: {}", record.seq, e);
+                    Ok(())
+                }
+                result => result,
+            }
+        })
+    }
+
+    /// 不经过 spool，直接发送一份报告
+    ///
+    /// 小于 `codec_min_size` 的报告不压缩；服务端不接受所选编码时改用服务端接受的编码重新发送。
+    fn send_direct(&self, command: u8, message_str: &str) -> Result<()> {
+        let codec = if message_str.len() < self.config.codec_min_size { CodecId::None } else { self.config.codec };
+        match self.send_with_codec(command, message_str, codec) {
+            Err(ClientError::CodecRejected { offered, accepted }) => {
+                println!("[Client-{}] 服务端不接受编码 {}，改用 {}", self.config.client_id, offered, accepted);
+                self.send_with_codec(command, message_str, accepted)
+            }
+            result => result,
+        }
+    }
+
+    fn send_with_codec(&self, command: u8, message_str: &str, codec: CodecId) -> Result<()> {
+
+        // 压缩字符串
+        let (compressed_data, compressed_len) =
+            data_process::compress_with(codec, message_str.as_bytes(), self.config.compression_level)?;
+        println!("字符串压缩前后长度是：{}-->{} ({})", message_str.len(), compressed_len, codec);
+
+        // 分片长度在握手时协商，由 client_thread 包装成 message_packet 数组
+        // 获取锁，保护通信过程
+        // 锁中毒只说明另一个调用方 panic 过，受保护的数据为空，可以继续使用
+        let _guard = VSOCK_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        client_thread_save::client_thread(&compressed_data, &self.config, command, codec)
+    }
+
+    /// 在一条连接上交错保存多份报告，返回每份报告的结果，顺序与 `reports` 一致
+    ///
+    /// 每份报告使用独立的 message_id；进程报告同样先做格式校验。
+    /// 不经过 spool，也不重试；需要协议 v2 和支持多路复用的服务端。连接失败时返回错误。
+    pub fn send_many(&self, reports: &[(u8, String)]) -> Result<Vec<Result<()>>> {
+        let _guard = VSOCK_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        let mut mux = Multiplexer::connect(&self.config)?;
+
+        let mut results = Vec::with_capacity(reports.len());
+        let mut started = Vec::new();
+        for (i, (command, message_str)) in reports.iter().enumerate() {
+            match self.compress_for_send(*command, message_str) {
+                Ok((codec, data)) => {
+                    mux.start(*command, codec, &data)?;
+                    started.push(i);
+                    results.push(Ok(()));
+                }
+                Err(e) => results.push(Err(e)),
+            }
+        }
+        for (i, (_, result)) in started.into_iter().zip(mux.finish()?) {
+            results[i] = result;
+        }
+        Ok(results)
+    }
+
+    /// 校验进程报告并按配置选择编码压缩
+    fn compress_for_send(&self, command: u8, message_str: &str) -> Result<(CodecId, Vec<u8>)> {
+        if command == constants::SAVE_PROCESS_COMMAND {
+            data_process::check_process_report(message_str, self.config.validation)?;
+        }
+        let codec = if message_str.len() < self.config.codec_min_size { CodecId::None } else { self.config.codec };
+        let (data, _) = data_process::compress_with(codec, message_str.as_bytes(), self.config.compression_level)?;
+        Ok((codec, data))
+    }
+
+    /// 流式保存：从 `reader` 边读取边压缩发送，返回压缩后的字节数
+    ///
+    /// 适合很大的报告；需要协议 v2，传输中断后不续传，也不经过 spool。
+    pub fn save_from_reader<R: Read>(&self, command: u8, reader: R) -> Result<u64> {
+        let _guard = VSOCK_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        client_thread_save::stream_thread(reader, &self.config, command)
+    }
+
+    /// 流式取回：边接收边解压，以 JSON 数组写入 `writer`，返回报告数
+    ///
+    /// 数据写出后无法撤回，因此失败时不重试。
+    pub fn dump_to_writer<W: Write>(&self, command: u8, writer: W) -> Result<usize> {
+        let _guard = VSOCK_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        client_thread_dump::stream_thread(&self.config, command, writer)
+    }
+
+    /// 使用指定的 dump 命令取回全部报告
+    pub fn dump(&self, command: u8) -> Result<Vec<u8>> {
+        // 获取锁，保护通信过程
+        let _guard = VSOCK_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+
+        // dump 不会修改服务端数据，失败后可以直接整体重试
+        utils::with_retry(&self.config.retry, "[Client-For-Dump]", |_| {
+            client_thread_dump::client_thread(&self.config, command)
+        })
+    }
+}
@@ -1,41 +1,38 @@
 // src/client_thread_dump.rs
-use vsock::{VsockStream, VsockAddr};
+use std::io::Write;
 use std::thread;
-use std::time::Duration;
+use crate::codec::{BoxWrite, CodecId, FinishWrite};
 use crate::utils;
-use crate::protocol::{MessagePacket, utils as protocol_utils};
-use anyhow::Result;
+use crate::config::ClientConfig;
+use crate::protocol::{MessageType, FramedStream, StartAck, StartRequest, Window, WindowReceiver, utils as protocol_utils};
+use crate::error::{ClientError, Result};
 use crate::protocol::consts;
 use crate::data_process;
+use crate::transport::Transport;
+use sha2::{Digest, Sha256};
 
-const MESSAGE_INTERVAL_MS: u64 = 100;
-
-pub fn client_thread(server_cid: u32, server_port: u32, command: u8) -> Result<Vec<u8>> {
-    let client_id = 1; // 简单起见，使用固定的 client_id
+pub fn client_thread(config: &ClientConfig, command: u8) -> Result<Vec<u8>> {
+    let client_id = config.client_id as usize;
+    let msg_id = utils::next_message_id();
     println!("[Client-{}] 黑匣子客户端正在启动...", client_id);
  
     // 连接到服务器
-    let addr = VsockAddr::new(server_cid, server_port);
-    let mut stream = match VsockStream::connect(&addr) {
-        Ok(s) => {
-            println!("[Client-{}] ✓ 已连接到服务端 CID:{} Port:{}", client_id, server_cid, server_port);
-            s
-        }
-        Err(e) => {
-            return Err(anyhow::anyhow!("[Client-{}] ✗ 连接失败: {:?}", client_id, e));
-        }
+    let mut stream = utils::connect(config, &format!("[Client-{}]", client_id))?;
+
+    // 1. 发送开始消息, 同时携带 msg_id 作为 message_id， 命令编号 作为 reserved
+    // 2. 等待 ACK，ACK 中携带服务端发送报告时使用的编码和窗口大小
+    let request = StartRequest {
+        codec: config.codec,
+        window: config.window_size,
+        max_packet_size: config.max_packet_size,
+        ..StartRequest::default()
     };
-
-    // 1. 发送开始消息, 同时携带 client_id 作为 message_id， 命令编号 作为 reserved
-    protocol_utils::send_start_message(&mut stream, client_id as u32, command)?;
-
-    // 2. 等待 ACK
-    if !protocol_utils::wait_for_ack(&mut stream, client_id as u32) {
-        return Err(anyhow::anyhow!("Server not ready"));
-    }
+    let ack = protocol_utils::start_handshake(&mut stream, msg_id, command, &request)?;
+    let codec = ack.codec;
+    stream.set_read_timeout(config.timeouts.ack);
 
     // 3. 发送 ACK
-    protocol_utils::send_ack_message(&mut stream, client_id as u32)?;
+    protocol_utils::send_ack_message(&mut stream, msg_id)?;
 
     /*
      * 接收 echo
@@ -54,41 +51,26 @@ pub fn client_thread(server_cid: u32, server_port: u32, command: u8) -> Result<V
      */
 
     let mut received_items: Vec<serde_json::Value> = Vec::new();
-
-    loop {
-        let (new_packets, all_end_received) = get_one_report(&mut stream, client_id)?;
-        // 没有记录需要dump
-        if new_packets.len() == 0 && all_end_received == true{
-            println!("[Client-{}] 没有需要 dump 的记录", client_id);
-            protocol_utils::send_ack_message(&mut stream, client_id as u32)?;
-            protocol_utils::wait_for_ack(&mut stream, client_id as u32);
-            break;
-        }
-
-        println!("[Client-{}] 成功接收 {} 个数据包", client_id, new_packets.len());
-
-        // 将数据包的 body 合并成一个 Vec<u8>
-        let combined_data: Vec<u8> = data_process::combine_message_bodies(&new_packets);
-
-        // 字符串解压成 紧凑的 JSON 字符串
-        let (decompressed_str, _len) = data_process::decompress_to_string(&combined_data)?;
-
-        // 尝试解析为 JSON
-        match serde_json::from_str::<serde_json::Value>(&decompressed_str) {
-            Ok(val) => {
+    let mut combined_data: Vec<u8> = Vec::new();
+
+    receive_reports(&mut stream, client_id, msg_id, &ack, |event| {
+        match event {
+            ReportEvent::Data(body) => combined_data.extend_from_slice(body),
+            ReportEvent::End => {
+                // 字符串解压成 紧凑的 JSON 字符串
+                let (decompressed_str, _len) = data_process::decompress_to_string_with(codec, &combined_data)?;
+                combined_data.clear();
+
+                // 解析失败时中止导出，不返回缺少报告的结果
+                let val = serde_json::from_str::<serde_json::Value>(&decompressed_str).map_err(|e| {
+                    eprintln!("[Client-{}] ✗ 解析 JSON 失败: {:?}.", client_id, e);
+                    ClientError::Json(e)
+                })?;
                 received_items.push(val);
-            },
-            Err(e) => {
-                eprintln!("[Client-{}] ✗ 解析 JSON 失败: {:?}.", client_id, e);
             }
         }
-        if all_end_received {
-            println!("[Client-{}] 收到 ALL_END 消息，所有传输结束", client_id);
-            protocol_utils::send_ack_message(&mut stream, client_id as u32)?;
-            protocol_utils::wait_for_ack(&mut stream, client_id as u32);
-            break;
-        }
-    }
+        Ok(())
+    })?;
 
     let mut json_bytes: Vec<u8> = Vec::new();
     if !received_items.is_empty() {
@@ -99,10 +81,10 @@ pub fn client_thread(server_cid: u32, server_port: u32, command: u8) -> Result<V
     }
 
     // 间隔
-    thread::sleep(Duration::from_millis(MESSAGE_INTERVAL_MS));
+    thread::sleep(config.message_interval);
 
     // 优雅关闭连接
-    utils::graceful_shutdown(&mut stream, "[Client-Thread-For-Dump]");
+    utils::graceful_shutdown(stream.get_mut(), "[Client-Thread-For-Dump]");
 
     println!("[Client-For-Dump] 完成。正在关闭连接。");
 
@@ -111,69 +93,213 @@ pub fn client_thread(server_cid: u32, server_port: u32, command: u8) -> Result<V
 
 
 
-fn get_one_report(stream: &mut VsockStream, client_id: usize) -> Result<(Vec<MessagePacket>, bool)> {
+/// 接收过程中交给调用方的事件
+enum ReportEvent<'a> {
+    /// 一个 DATA 分片的消息体（压缩数据）
+    Data(&'a [u8]),
+    /// 当前报告接收完毕并通过摘要校验
+    End,
+}
+
+/// 依次接收全部报告直到 ALL_END，返回报告数
+///
+/// 每份报告按握手确认的窗口接收；旧版服务端不支持窗口，每收到 5 个分片回复一次 ACK。
+fn receive_reports<T, F>(stream: &mut FramedStream<T>, client_id: usize, msg_id: u32, ack: &StartAck, mut on_event: F) -> Result<usize>
+where
+    T: Transport,
+    F: FnMut(ReportEvent<'_>) -> Result<()>,
+{
+    let window = Window::negotiated(ack.window, consts::LEGACY_DUMP_ACK_INTERVAL);
+    let mut reports = 0;
+    loop {
+        let (chunks, all_end_received) = get_one_report(stream, client_id, msg_id, ack.codec, window, &mut on_event)?;
+        // 没有记录需要dump
+        if chunks == 0 && all_end_received {
+            if reports == 0 {
+                println!("[Client-{}] 没有需要 dump 的记录", client_id);
+            }
+        } else {
+            println!("[Client-{}] 成功接收 {} 个数据包", client_id, chunks);
+            if let Err(e) = on_event(ReportEvent::End) {
+                return Err(abort(stream, client_id, msg_id, e));
+            }
+            reports += 1;
+        }
 
-    let mut msg_packets: Vec<MessagePacket> = Vec::new();
-    let mut received_data_size: u32 = 0;
+        if all_end_received {
+            println!("[Client-{}] 收到 ALL_END 消息，所有传输结束", client_id);
+            protocol_utils::send_ack_message(stream, msg_id)?;
+            let _ = protocol_utils::wait_for_ack(stream, msg_id);
+            return Ok(reports);
+        }
+    }
+}
+
+/// 流式接收：边接收边解压写入 `writer`，内存占用与报告大小无关，返回报告数
+///
+/// 输出为 JSON 数组，元素为服务端保存的原始报告（不重新解析）。
+/// 数据已写出后无法重放，因此不重试；摘要校验失败时已写出的部分不会撤回。
+pub fn stream_thread<W: Write>(config: &ClientConfig, command: u8, writer: W) -> Result<usize> {
+    let client_id = config.client_id as usize;
+    let msg_id = utils::next_message_id();
+    println!("[Client-{}] 黑匣子客户端正在启动（流式）...", client_id);
+
+    let mut stream = utils::connect(config, &format!("[Client-{}]", client_id))?;
+    let request = StartRequest {
+        codec: config.codec,
+        window: config.window_size,
+        max_packet_size: config.max_packet_size,
+        ..StartRequest::default()
+    };
+    let ack = protocol_utils::start_handshake(&mut stream, msg_id, command, &request)?;
+    let codec = ack.codec;
+    stream.set_read_timeout(config.timeouts.ack);
+    protocol_utils::send_ack_message(&mut stream, msg_id)?;
+
+    // writer 在两个报告之间归还，报告内部交给解压器
+    let mut writer: Option<BoxWrite<'_>> = Some(Box::new(writer));
+    let mut decoder: Option<Box<dyn FinishWrite<'_> + '_>> = None;
+    let mut reports = 0usize;
+    let result = receive_reports(&mut stream, client_id, msg_id, &ack, |event| {
+        match event {
+            ReportEvent::Data(body) => {
+                if decoder.is_none() {
+                    let mut w = writer.take().ok_or_else(|| ClientError::ProtocolViolation("输出已关闭".to_string()))?;
+                    w.write_all(if reports == 0 { b"[" } else { b"," }).map_err(ClientError::Io)?;
+                    decoder = Some(codec.codec().decoder(w).map_err(ClientError::Decompress)?);
+                }
+                if let Some(d) = decoder.as_mut() {
+                    d.write_all(body).map_err(ClientError::Decompress)?;
+                }
+            }
+            ReportEvent::End => {
+                if let Some(d) = decoder.take() {
+                    writer = Some(d.finish().map_err(ClientError::Decompress)?);
+                }
+                reports += 1;
+            }
+        }
+        Ok(())
+    });
+
+    utils::graceful_shutdown(stream.get_mut(), "[Client-Thread-For-Dump]");
+    result?;
+
+    let mut writer = writer.ok_or_else(|| ClientError::ProtocolViolation("报告未正常结束".to_string()))?;
+    writer.write_all(if reports == 0 { b"[]" } else { b"]" }).map_err(ClientError::Io)?;
+    writer.flush().map_err(ClientError::Io)?;
+
+    println!("[Client-For-Dump] 完成（流式），共 {} 份报告", reports);
+    Ok(reports)
+}
+
+/// 接收一份报告的全部分片，每个 DATA 消息体交给 `on_event`，返回 (分片数, 是否收到 ALL_END)
+fn get_one_report<T, F>(stream: &mut FramedStream<T>, client_id: usize, msg_id: u32, codec: CodecId, window: Window, on_event: &mut F) -> Result<(u32, bool)>
+where
+    T: Transport,
+    F: FnMut(ReportEvent<'_>) -> Result<()>,
+{
+    let mut receiver = WindowReceiver::new(window, msg_id, 0);
+    let mut received_chunks: u32 = 0;
+    // 消息头中的总大小为 u32；累计大小使用 u64，收到的数据超过 4GiB 时不会回绕成与总大小相等的值
+    let mut received_data_size: u64 = 0;
+    // DATA 消息头中的 (总大小, 分片数)；流式发送时只有最后一个 DATA 携带
+    let mut data_totals: (u32, u32) = (0, 0);
     let mut is_all_reports_have_been_received = false;
+    // 边接收边计算负载摘要，END 消息到达时与其携带的摘要比对
+    let mut hasher = Sha256::new();
 
     loop {
 
-        // 1. 读取消息包
-        let mut buf = [0u8; consts::MAX_MESSAGE_PACKET_SIZE];
-        let packet = protocol_utils::receive_data_message(stream, &mut buf)?;
-
-        // 2. 检查消息类型, 以及消息完整性
-        if packet.header.msg_type == consts::MSG_TYPE_END 
-            && msg_packets.len() == msg_packets[0].header.chunk_count as usize
-            && msg_packets[0].header.total_size == received_data_size {
+        // 1. 读取一个完整的消息包，服务端报告错误时转换为 ClientError::Remote
+        let packet = protocol_utils::check_error(protocol_utils::receive_data_message(stream)?)?;
+
+        // 2. 检查消息类型, 以及消息完整性（以 END 携带的总大小和分片数为准）
+        if packet.header.msg_type == MessageType::End {
+            // 旧版服务端的 END 不携带总大小和分片数 (0/0)，改用 DATA 消息头中的值
+            let (expected_size, expected_chunks) = match (packet.header.total_size, packet.header.chunk_count) {
+                (0, 0) => data_totals,
+                totals => totals,
+            };
+            if received_chunks == 0
+                || received_chunks != expected_chunks
+                || received_data_size != expected_size as u64 {
+                return Err(abort(stream, client_id, msg_id, ClientError::ProtocolViolation(format!(
+                    "数据不完整: 期望 {} 字节 / {} 个分片, 收到 {} 字节 / {} 个分片",
+                    expected_size, expected_chunks, received_data_size, received_chunks
+                ))));
+            }
             println!("收到 END 消息，本次传输结束");
+
+            // v2 的 END 消息携带完整负载摘要，解压前先校验
+            if let Err(e) = protocol_utils::verify_payload_digest(&packet, hasher.finalize_reset().as_slice()) {
+                return Err(abort(stream, client_id, msg_id, e));
+            }
             
-            protocol_utils::send_ack_message(stream, client_id as u32)?;
+            protocol_utils::send_ack_message(stream, msg_id)?;
 
-            protocol_utils::wait_for_ack(stream, client_id as u32);
+            let _ = protocol_utils::wait_for_ack(stream, msg_id);
 
             break;
         }
 
-        if packet.header.msg_type == consts::MSG_TYPE_ALL_END {
+        if packet.header.msg_type == MessageType::AllEnd {
             println!("收到 ALL_END 消息，所有传输结束");
             is_all_reports_have_been_received = true;
             break;
         }
 
-        if packet.header.msg_type != consts::MSG_TYPE_DATA {
+        if packet.header.msg_type != MessageType::Data {
             println!("收到非 DATA/END 消息: type={}, 忽略", packet.header.msg_type);
             continue;
         }
 
         // 3. 验证消息ID
-        if packet.header.message_id != client_id as u32 {
-            return Err(anyhow::anyhow!("Unexpected message ID: {}, expected: {}", packet.header.message_id, client_id));
+        if packet.header.message_id != msg_id {
+            return Err(abort(stream, client_id, msg_id, ClientError::ProtocolViolation(format!(
+                "Unexpected message ID: {:#x}, expected: {:#x}", packet.header.message_id, msg_id
+            ))));
+        }
+        // reserved 字段携带编码，必须与握手时确认的一致
+        if packet.header.reserved != u8::from(codec) {
+            return Err(abort(stream, client_id, msg_id, ClientError::ProtocolViolation(format!(
+                "分片编码 {} 与握手确认的编码 {} 不一致", packet.header.reserved, codec
+            ))));
         }
         // println!("收到分片: ID={}, Index={}/{}", packet.header.message_id, packet.header.chunk_index, packet.header.chunk_count);
+        if packet.header.chunk_count != 0 {
+            data_totals = (packet.header.total_size, packet.header.chunk_count);
+        }
 
-        // 4. 统计消息体累计大小
-        received_data_size += packet.body.len() as u32;
-
-        // 5. 验证校验和
-        let calculated_checksum = protocol_utils::calculate_checksum(&packet.body);
-        if calculated_checksum != packet.header.checksum {
-
-            utils::graceful_shutdown(stream, "[Client-{}]");
+        // 4. 按窗口接收：校验和检查、乱序缓存和 ACK 由 WindowReceiver 负责，按顺序交付消息体
+        let delivered = match receiver.receive(stream, packet) {
+            Ok(delivered) => delivered,
+            Err(e) => return Err(abort(stream, client_id, msg_id, e)),
+        };
 
-            return Err(anyhow::anyhow!("Checksum mismatch for chunk {}", packet.header.chunk_index));
-        }
+        for body in delivered {
+            // 5. 统计消息体累计大小并计算摘要
+            received_data_size += body.len() as u64;
+            hasher.update(&body);
 
-        // 6. 每收到5个分片发送一次ACK
-        if packet.header.chunk_index % 5 == 4 {
-            protocol_utils::send_ack_message(stream, client_id as u32)?;
+            // 6. 交给调用方处理，处理失败同样通知服务端
+            if let Err(e) = on_event(ReportEvent::Data(&body)) {
+                return Err(abort(stream, client_id, msg_id, e));
+            }
+            received_chunks += 1;
         }
-
-        // 7. 构造 MessagePacket 并存储
-        msg_packets.push(packet);
     }
 
 
-    Ok((msg_packets, is_all_reports_have_been_received))
-}
\ No newline at end of file
+    Ok((received_chunks, is_all_reports_have_been_received))
+}
+
+/// 中止接收：向服务端发送 ERROR 说明原因后关闭连接，返回原错误
+fn abort<T: Transport>(stream: &mut FramedStream<T>, client_id: usize, msg_id: u32, e: ClientError) -> ClientError {
+    eprintln!("[Client-{}] ✗ 接收失败，通知服务端: {}", client_id, e);
+    if let Err(send_err) = protocol_utils::send_error_message(stream, msg_id, &e.to_report()) {
+        eprintln!("[Client-{}] ✗ 发送 ERROR 失败: {}", client_id, send_err);
+    }
+    utils::graceful_shutdown(stream.get_mut(), "[Client-{}]");
+    e
+}
@@ -1,61 +1,227 @@
 // src/client_thread_save.rs
-use vsock::{VsockStream, VsockAddr};
+use std::io::{self, Read};
+use sha2::{Digest, Sha256};
+use crate::codec::CodecId;
 use crate::utils;
-use crate::protocol::MessagePacket;
+use crate::config::ClientConfig;
+use crate::protocol::{control, MessagePacket, MessageType, StartRequest, Window, WindowSender};
+use crate::data_process;
+use crate::protocol::consts::{PROTOCOL_VERSION_V1, PROTOCOL_VERSION_V2};
 use crate::protocol::utils as protocol_utils;
-use anyhow::Result;
+use crate::error::{ClientError, Result};
 
 
-pub fn client_thread(msg_packets: Vec<MessagePacket>, server_cid: u32, server_port: u32, command: u8) -> Result<()> {
-    let msg_id = 1; // 简单起见，使用固定的 msg_id
-    println!("[Client-{}] 黑匣子客户端正在启动...", msg_id);
- 
-    // 连接到服务器
-    let addr = VsockAddr::new(server_cid, server_port);
-    let mut stream = match VsockStream::connect(&addr) {
-        Ok(s) => {
-            println!("[Client-{}] ✓ 已连接到服务端 CID:{} Port:{}", msg_id, server_cid, server_port);
-            s
-        }
-        Err(e) => {
-            return Err(anyhow::anyhow!("[Client-{}] ✗ 连接失败: {:?}", msg_id, e));
+/// 发送一份报告，连接失败或传输中断时按重试策略重连并续传
+///
+/// `data` 已按 `codec` 压缩；服务端不接受该编码时返回 `ClientError::CodecRejected`。
+/// 按提出的消息包长度分片，服务端确认更小的长度时重新分片并重新发送。
+pub fn client_thread(data: &[u8], config: &ClientConfig, command: u8, codec: CodecId) -> Result<()> {
+    let log_prefix = format!("[Client-{}]", config.client_id);
+    println!("{} 黑匣子客户端正在启动...", log_prefix);
+
+    // 重试期间保持不变，服务端据此找到上一次中断的传输
+    let msg_id = utils::next_message_id();
+    let transfer_id = utils::random_u64();
+    // v1 消息头没有消息体长度字段，只能使用固定的分片长度
+    let mut packet_size = if config.protocol_version == PROTOCOL_VERSION_V1 { 0 } else { config.max_packet_size };
+
+    loop {
+        let msg_packets = wrap_packets(data, msg_id, codec, packet_size);
+        let request = start_request(&msg_packets, transfer_id, codec, config.window_size, packet_size);
+
+        let result = utils::with_retry(&config.retry, &log_prefix, |attempt| {
+            if attempt > 1 {
+                println!("{} 第 {} 次尝试，transfer_id={:#x}", log_prefix, attempt, request.transfer_id);
+            }
+            send_once(&msg_packets, config, msg_id, command, &request)
+        });
+        match result {
+            Err(ClientError::PacketSizeRejected { offered, accepted }) => {
+                println!("{} 服务端不接受消息包长度 {}，改用 {} 重新分片", log_prefix, offered, accepted);
+                packet_size = accepted;
+            }
+            result => break result?,
         }
-    };
+    }
 
-    println!("[Client-{}] 准备发送数据，负责 {} 个消息包", msg_id, msg_packets.len());
+    println!("[Client-For-Save] 完成。正在关闭连接。");
+
+    Ok(())
+}
+
+/// 一次完整的连接 + 握手 + 发送
+fn send_once(msg_packets: &[MessagePacket], config: &ClientConfig, msg_id: u32, command: u8, request: &StartRequest) -> Result<()> {
+    let client_id = config.client_id;
+
+    // 连接到服务器
+    let mut stream = utils::connect(config, &format!("[Client-{}]", client_id))?;
+
+    println!("[Client-{}] 准备发送数据，负责 {} 个消息包 (message_id={:#x})", client_id, msg_packets.len(), msg_id);
 
     // 1. 发送开始消息, 同时携带 msg_id 作为 message_id， 命令编号 作为 reserved
-    protocol_utils::send_start_message(&mut stream, msg_id as u32, command)?;
+    // 2. 等待ACK，ACK 中携带服务端已确认的分片数和接受的编码
+    let ack = protocol_utils::start_handshake(&mut stream, msg_id, command, request)?;
+    stream.set_read_timeout(config.timeouts.ack);
 
-    // 2. 等待ACK
-    if !protocol_utils::wait_for_ack(&mut stream, msg_id as u32) {
-        return Err(anyhow::anyhow!("Server not ready"));
+    if ack.codec != request.codec {
+        utils::graceful_shutdown(stream.get_mut(), "[Client-{}]");
+        return Err(ClientError::CodecRejected { offered: request.codec, accepted: ack.codec });
+    }
+    // 数据已按提出的长度分片，服务端确认的长度不同时需要重新分片
+    if ack.max_packet_size != request.max_packet_size {
+        utils::graceful_shutdown(stream.get_mut(), "[Client-{}]");
+        return Err(ClientError::PacketSizeRejected { offered: request.max_packet_size, accepted: ack.max_packet_size });
     }
+    let resume_from = ack.resume_from;
 
-    // 3. 分片发送数据
-    for (_, datamsg) in msg_packets.iter().enumerate() {
-        // println!("[Client-{}] 发送第 {} 数据包 ", msg_id, index);
-        protocol_utils::send_data_message(&mut stream, datamsg)?;
-    
-        if !protocol_utils::wait_for_ack(&mut stream, msg_id as u32) {
-            utils::graceful_shutdown(&mut stream, "[Client-{}]");
-            return Err(anyhow::anyhow!("Transmission interrupted"));
+    if resume_from > request.chunk_count {
+        utils::graceful_shutdown(stream.get_mut(), "[Client-{}]");
+        return Err(ClientError::ProtocolViolation(format!(
+            "续传位置 {} 超出分片总数 {}", resume_from, request.chunk_count
+        )));
+    }
+    if resume_from > 0 {
+        println!("[Client-{}] 服务端已确认 {} 个分片，从第 {} 个分片续传", client_id, resume_from, resume_from);
+    }
+
+    // 3. 按窗口分片发送数据，全部确认后发送携带负载摘要的 END 消息（数组最后一个消息包）
+    let window = Window::negotiated(ack.window, 1);
+    let mut sender = WindowSender::new(window, msg_id, resume_from);
+    let result = (|| -> Result<()> {
+        let (end, data) = msg_packets.split_last().ok_or_else(|| ClientError::ProtocolViolation("没有待发送的消息包".to_string()))?;
+        for datamsg in data.iter().skip(resume_from as usize) {
+            sender.send(&mut stream, datamsg.clone())?;
         }
+        sender.finish(&mut stream)?;
+        protocol_utils::send_data_message(&mut stream, end)?;
+        protocol_utils::wait_for_ack(&mut stream, msg_id)?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        eprintln!("[Client-{}] ✗ 传输中断: {}", client_id, e);
+        utils::graceful_shutdown(stream.get_mut(), "[Client-{}]");
+        return Err(e);
     }
+    println!("[Client-{}] ✓ 传输完成，服务器已确认 (窗口 {})", client_id, window.size);
 
-    // 4. 发送结束消息
-    protocol_utils::send_end_message(&mut stream, msg_id as u32)?;
+    // 4. 优雅关闭连接
+    utils::graceful_shutdown(stream.get_mut(), "[Client-{}]");
 
-    // 5. 等待ACK
-    if !protocol_utils::wait_for_ack(&mut stream, msg_id as u32) {
-        return Err(anyhow::anyhow!("Server not ready"));
+    Ok(())
+}
+
+/// 按消息包长度分片，reserved 字段携带编码；`packet_size` 为 0 时使用固定的分片长度
+fn wrap_packets(data: &[u8], msg_id: u32, codec: CodecId, packet_size: u32) -> Vec<MessagePacket> {
+    let mut msg_packets = data_process::wrap_message_packets_with(data.to_vec(), control::max_body_size(packet_size));
+    for packet in &mut msg_packets {
+        packet.header.set_message_id(msg_id);
+        packet.header.set_reserved(codec.into());
     }
-    println!("[Client-{}] ✓ 传输完成，服务器已确认", msg_id);
+    msg_packets
+}
 
-    // 6. 优雅关闭连接
-    utils::graceful_shutdown(&mut stream, "[Client-{}]");
+/// 由待发送的数据包生成 START 参数
+fn start_request(msg_packets: &[MessagePacket], transfer_id: u64, codec: CodecId, window: u32, max_packet_size: u32) -> StartRequest {
+    let chunk_count = msg_packets
+        .iter()
+        .filter(|p| p.header.msg_type == MessageType::Data)
+        .count() as u32;
+    let total_size = msg_packets.first().map(|p| p.header.total_size).unwrap_or(0);
+    StartRequest { transfer_id, total_size, chunk_count, codec, window, max_packet_size }
+}
 
-    println!("[Client-For-Save] 完成。正在关闭连接。");
+/// 流式发送：边读取边压缩边分片，内存占用与报告大小无关，返回压缩后的字节数
+///
+/// 总大小在读完之前未知：START 和 DATA 中 total_size / chunk_count 为 0，由最后一个 DATA 和 END 携带最终值，
+/// 因此只支持 v2（v1 接收方依赖 total_size 推导消息体长度）。数据读出后无法重放，
+/// 只有连接和握手阶段会重试，传输中断后不续传。
+/// 此时还没有发送任何数据，服务端不接受 `config.codec` 时直接改用服务端接受的编码。
+pub fn stream_thread<R: Read>(reader: R, config: &ClientConfig, command: u8) -> Result<u64> {
+    if config.protocol_version != PROTOCOL_VERSION_V2 {
+        return Err(ClientError::Config("流式保存需要协议 v2".to_string()));
+    }
+    let msg_id = utils::next_message_id();
+    let log_prefix = format!("[Client-{}]", config.client_id);
+    println!("{} 黑匣子客户端正在启动（流式）...", log_prefix);
 
-    Ok(())
-}
\ No newline at end of file
+    // transfer_id 为 0，服务端不会保留未完成的传输
+    let request = StartRequest {
+        codec: config.codec,
+        window: config.window_size,
+        max_packet_size: config.max_packet_size,
+        ..StartRequest::default()
+    };
+    let (mut stream, ack) = utils::with_retry(&config.retry, &log_prefix, |_| {
+        let mut stream = utils::connect(config, &log_prefix)?;
+        let ack = protocol_utils::start_handshake(&mut stream, msg_id, command, &request)?;
+        Ok((stream, ack))
+    })?;
+    stream.set_read_timeout(config.timeouts.ack);
+    if ack.codec != request.codec {
+        println!("{} 服务端不接受编码 {}，改用 {}", log_prefix, request.codec, ack.codec);
+    }
+
+    let mut encoder = ack.codec.codec().encoder(Box::new(reader), config.compression_level)?;
+    let mut hasher = Sha256::new();
+    let mut sender = WindowSender::new(Window::negotiated(ack.window, 1), msg_id, 0);
+    let mut buf = vec![0u8; ack.max_body_size()];
+    let mut next_buf = vec![0u8; ack.max_body_size()];
+    let mut total_size: u32 = 0;
+    let mut chunk_index: u32 = 0;
+
+    let result = (|| -> Result<()> {
+        // 多读一个分片，最后一个 DATA 携带总大小和分片数，接收方据此确认最后不足一组的分片
+        let mut n = read_full(&mut encoder, &mut buf)?;
+        while n > 0 {
+            let next_n = if n < buf.len() { 0 } else { read_full(&mut encoder, &mut next_buf)? };
+            hasher.update(&buf[..n]);
+            total_size = total_size
+                .checked_add(n as u32)
+                .ok_or_else(|| ClientError::Config("压缩后的报告超过 4GiB".to_string()))?;
+
+            let (total, count) = if next_n == 0 { (total_size, chunk_index + 1) } else { (0, 0) };
+            let mut packet = MessagePacket::new(MessageType::Data, total, chunk_index, count);
+            packet.header.set_message_id(msg_id);
+            packet.header.set_reserved(ack.codec.into());
+            packet.set_body(buf[..n].to_vec());
+            sender.send(&mut stream, packet)?;
+
+            chunk_index += 1;
+            std::mem::swap(&mut buf, &mut next_buf);
+            n = next_n;
+        }
+        sender.finish(&mut stream)?;
+
+        // END 携带最终的总大小、分片数和负载摘要
+        let mut end = MessagePacket::new(MessageType::End, total_size, chunk_index, chunk_index);
+        end.header.set_message_id(msg_id);
+        end.header.set_reserved(ack.codec.into());
+        end.set_body(hasher.finalize().to_vec());
+        protocol_utils::send_data_message(&mut stream, &end)?;
+        protocol_utils::wait_for_ack(&mut stream, msg_id)?;
+        Ok(())
+    })();
+
+    if let Err(e) = &result {
+        eprintln!("{} ✗ 流式传输中断: {}", log_prefix, e);
+    }
+    utils::graceful_shutdown(stream.get_mut(), "[Client-{}]");
+    result?;
+
+    println!("{} ✓ 流式传输完成，共 {} 个分片，{} 字节", log_prefix, chunk_index, total_size);
+    Ok(total_size as u64)
+}
+
+/// 读满缓冲区或读到末尾，返回读取的字节数
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(ClientError::Io(e)),
+        }
+    }
+    Ok(filled)
+}
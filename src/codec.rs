@@ -0,0 +1,447 @@
+// src/codec.rs
+// 负载压缩编码：zlib / none / gzip / zstd / lz4
+//
+// 编码编号在 START 消息体中提出，由服务端在 ACK 消息体中确认，
+// 之后每个 DATA / END 消息的 reserved 字段携带该编号。旧版对端不携带编号，视为 zlib。
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+use flate2::Compression;
+use crate::error::ClientError;
+
+// 流式编码时每次从输入读取的字节数
+const PUMP_BUFFER_SIZE: usize = 8 * 1024;
+
+/// 编码编号，写入协议的 1 字节值
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(u8)]
+pub enum CodecId {
+    /// 旧版协议唯一支持的编码，编号为 0 以兼容未填写 reserved 的对端
+    #[default]
+    Zlib = 0,
+    /// 不压缩，适合很小的报告
+    None = 1,
+    Gzip = 2,
+    Zstd = 3,
+    Lz4 = 4,
+}
+
+impl CodecId {
+    pub const ALL: [CodecId; 5] = [CodecId::Zlib, CodecId::None, CodecId::Gzip, CodecId::Zstd, CodecId::Lz4];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CodecId::Zlib => "zlib",
+            CodecId::None => "none",
+            CodecId::Gzip => "gzip",
+            CodecId::Zstd => "zstd",
+            CodecId::Lz4 => "lz4",
+        }
+    }
+
+    /// 编号对应的编码实现
+    pub fn codec(self) -> &'static dyn Codec {
+        match self {
+            CodecId::Zlib => &Zlib,
+            CodecId::None => &Identity,
+            CodecId::Gzip => &Gzip,
+            CodecId::Zstd => &Zstd,
+            CodecId::Lz4 => &Lz4,
+        }
+    }
+}
+
+impl From<CodecId> for u8 {
+    fn from(id: CodecId) -> Self {
+        id as u8
+    }
+}
+
+impl TryFrom<u8> for CodecId {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        CodecId::ALL.into_iter().find(|id| *id as u8 == value).ok_or(value)
+    }
+}
+
+impl FromStr for CodecId {
+    type Err = ClientError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CodecId::ALL
+            .into_iter()
+            .find(|id| id.name() == s)
+            .ok_or_else(|| ClientError::Config(format!("未知编码: {} (可选 zlib/none/gzip/zstd/lz4)", s)))
+    }
+}
+
+impl fmt::Display for CodecId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// 被包装的输出
+pub type BoxWrite<'a> = Box<dyn Write + 'a>;
+
+/// 写入端解码器，`finish` 写出剩余数据并归还内部的 writer
+pub trait FinishWrite<'a>: Write {
+    fn finish(self: Box<Self>) -> io::Result<BoxWrite<'a>>;
+}
+
+/// 压缩编码
+///
+/// `level` 沿用 zlib 的 0-9：zstd 中 0 表示其默认级别，lz4 和 none 忽略该参数。
+pub trait Codec: Send + Sync {
+    fn id(&self) -> CodecId;
+
+    fn compress(&self, data: &[u8], level: u32) -> io::Result<Vec<u8>>;
+
+    fn decompress(&self, data: &[u8]) -> io::Result<Vec<u8>>;
+
+    /// 读取端编码：从返回值读出的是 `reader` 内容压缩后的数据
+    fn encoder<'a>(&self, reader: Box<dyn Read + 'a>, level: u32) -> io::Result<Box<dyn Read + 'a>>;
+
+    /// 写入端解码：写入压缩数据，解压后的内容写到 `writer`
+    fn decoder<'a>(&self, writer: BoxWrite<'a>) -> io::Result<Box<dyn FinishWrite<'a> + 'a>>;
+}
+
+struct Zlib;
+struct Identity;
+struct Gzip;
+struct Zstd;
+struct Lz4;
+
+impl Codec for Zlib {
+    fn id(&self) -> CodecId {
+        CodecId::Zlib
+    }
+
+    fn compress(&self, data: &[u8], level: u32) -> io::Result<Vec<u8>> {
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), Compression::new(level));
+        encoder.write_all(data)?;
+        encoder.finish()
+    }
+
+    fn decompress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        flate2::read::ZlibDecoder::new(data).read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    fn encoder<'a>(&self, reader: Box<dyn Read + 'a>, level: u32) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(Box::new(flate2::read::ZlibEncoder::new(reader, Compression::new(level))))
+    }
+
+    fn decoder<'a>(&self, writer: BoxWrite<'a>) -> io::Result<Box<dyn FinishWrite<'a> + 'a>> {
+        Ok(Box::new(flate2::write::ZlibDecoder::new(writer)))
+    }
+}
+
+impl<'a> FinishWrite<'a> for flate2::write::ZlibDecoder<BoxWrite<'a>> {
+    fn finish(self: Box<Self>) -> io::Result<BoxWrite<'a>> {
+        (*self).finish()
+    }
+}
+
+impl Codec for Identity {
+    fn id(&self) -> CodecId {
+        CodecId::None
+    }
+
+    fn compress(&self, data: &[u8], _level: u32) -> io::Result<Vec<u8>> {
+        Ok(data.to_vec())
+    }
+
+    fn decompress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        Ok(data.to_vec())
+    }
+
+    fn encoder<'a>(&self, reader: Box<dyn Read + 'a>, _level: u32) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(reader)
+    }
+
+    fn decoder<'a>(&self, writer: BoxWrite<'a>) -> io::Result<Box<dyn FinishWrite<'a> + 'a>> {
+        Ok(Box::new(Passthrough(writer)))
+    }
+}
+
+/// 不做处理，直接写入内部 writer
+struct Passthrough<W>(W);
+
+impl<W: Write> Write for Passthrough<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl<'a> FinishWrite<'a> for Passthrough<BoxWrite<'a>> {
+    fn finish(self: Box<Self>) -> io::Result<BoxWrite<'a>> {
+        Ok(self.0)
+    }
+}
+
+impl Codec for Gzip {
+    fn id(&self) -> CodecId {
+        CodecId::Gzip
+    }
+
+    fn compress(&self, data: &[u8], level: u32) -> io::Result<Vec<u8>> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), Compression::new(level));
+        encoder.write_all(data)?;
+        encoder.finish()
+    }
+
+    fn decompress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        flate2::read::GzDecoder::new(data).read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    fn encoder<'a>(&self, reader: Box<dyn Read + 'a>, level: u32) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(Box::new(flate2::read::GzEncoder::new(reader, Compression::new(level))))
+    }
+
+    fn decoder<'a>(&self, writer: BoxWrite<'a>) -> io::Result<Box<dyn FinishWrite<'a> + 'a>> {
+        Ok(Box::new(flate2::write::GzDecoder::new(writer)))
+    }
+}
+
+impl<'a> FinishWrite<'a> for flate2::write::GzDecoder<BoxWrite<'a>> {
+    fn finish(self: Box<Self>) -> io::Result<BoxWrite<'a>> {
+        (*self).finish()
+    }
+}
+
+impl Codec for Zstd {
+    fn id(&self) -> CodecId {
+        CodecId::Zstd
+    }
+
+    fn compress(&self, data: &[u8], level: u32) -> io::Result<Vec<u8>> {
+        zstd::stream::encode_all(data, level as i32)
+    }
+
+    fn decompress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        zstd::stream::decode_all(data)
+    }
+
+    fn encoder<'a>(&self, reader: Box<dyn Read + 'a>, level: u32) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(Box::new(zstd::stream::read::Encoder::new(reader, level as i32)?))
+    }
+
+    fn decoder<'a>(&self, writer: BoxWrite<'a>) -> io::Result<Box<dyn FinishWrite<'a> + 'a>> {
+        Ok(Box::new(zstd::stream::write::Decoder::new(writer)?))
+    }
+}
+
+impl<'a> FinishWrite<'a> for zstd::stream::write::Decoder<'static, BoxWrite<'a>> {
+    fn finish(mut self: Box<Self>) -> io::Result<BoxWrite<'a>> {
+        // 解码器内部可能还有未写出的数据
+        self.flush()?;
+        Ok(self.into_inner())
+    }
+}
+
+impl Codec for Lz4 {
+    fn id(&self) -> CodecId {
+        CodecId::Lz4
+    }
+
+    fn compress(&self, data: &[u8], _level: u32) -> io::Result<Vec<u8>> {
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+        encoder.write_all(data)?;
+        encoder.finish().map_err(io::Error::other)
+    }
+
+    fn decompress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        lz4_flex::frame::FrameDecoder::new(data).read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    fn encoder<'a>(&self, reader: Box<dyn Read + 'a>, _level: u32) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(Box::new(Lz4ReadEncoder {
+            reader,
+            encoder: Some(lz4_flex::frame::FrameEncoder::new(Vec::new())),
+            out: Vec::new(),
+            pos: 0,
+        }))
+    }
+
+    fn decoder<'a>(&self, writer: BoxWrite<'a>) -> io::Result<Box<dyn FinishWrite<'a> + 'a>> {
+        Ok(Box::new(Lz4WriteDecoder { writer, decoder: lz4_flex::frame::FrameDecoder::new(Lz4Input::default()) }))
+    }
+}
+
+/// lz4_flex 只提供写入端编码器，这里把输入逐块写入编码器，再从其输出缓冲区读出
+struct Lz4ReadEncoder<R> {
+    reader: R,
+    encoder: Option<lz4_flex::frame::FrameEncoder<Vec<u8>>>,
+    out: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> Read for Lz4ReadEncoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.pos < self.out.len() {
+                let n = buf.len().min(self.out.len() - self.pos);
+                buf[..n].copy_from_slice(&self.out[self.pos..self.pos + n]);
+                self.pos += n;
+                return Ok(n);
+            }
+            let Some(encoder) = self.encoder.as_mut() else { return Ok(0) };
+
+            let mut chunk = [0u8; PUMP_BUFFER_SIZE];
+            let n = self.reader.read(&mut chunk)?;
+            if n > 0 {
+                encoder.write_all(&chunk[..n])?;
+                self.out = std::mem::take(encoder.get_mut());
+            } else if let Some(encoder) = self.encoder.take() {
+                self.out = encoder.finish().map_err(io::Error::other)?;
+            }
+            self.pos = 0;
+        }
+    }
+}
+
+/// lz4_flex 只提供读取端解码器：按帧格式找出已完整到达的帧头和数据块，只把这些字节交给解码器，
+/// 因此每收到一个完整的数据块就能解出一块，缓存的压缩数据不超过一个数据块
+struct Lz4WriteDecoder<W> {
+    writer: W,
+    decoder: lz4_flex::frame::FrameDecoder<Lz4Input>,
+}
+
+impl<W: Write> Lz4WriteDecoder<W> {
+    /// 解出所有已完整到达的数据块并写到 writer
+    fn drain(&mut self) -> io::Result<()> {
+        let mut buf = [0u8; PUMP_BUFFER_SIZE];
+        loop {
+            let before = self.decoder.get_ref().pos;
+            let n = self.decoder.read(&mut buf)?;
+            if n > 0 {
+                self.writer.write_all(&buf[..n])?;
+            } else if self.decoder.get_ref().pos == before {
+                break;
+            }
+        }
+        self.decoder.get_mut().compact();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Lz4WriteDecoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let input = self.decoder.get_mut();
+        input.data.extend_from_slice(buf);
+        input.scan()?;
+        self.drain()?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<'a> FinishWrite<'a> for Lz4WriteDecoder<BoxWrite<'a>> {
+    fn finish(mut self: Box<Self>) -> io::Result<BoxWrite<'a>> {
+        self.drain()?;
+        let input = self.decoder.get_ref();
+        if input.frame.is_some() || input.pos < input.data.len() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "lz4 数据不完整"));
+        }
+        Ok(self.writer)
+    }
+}
+
+// lz4 帧格式: magic(4) | FLG(1) | BD(1) | [content_size(8)] | [dict_id(4)] | HC(1)
+//             { block_size(4) | data | [block_checksum(4)] }* | end_mark(4) | [content_checksum(4)]
+const LZ4_FRAME_MAGIC: u32 = 0x184D_2204;
+const LZ4_FLG_BLOCK_CHECKSUM: u8 = 0x10;
+const LZ4_FLG_CONTENT_SIZE: u8 = 0x08;
+const LZ4_FLG_CONTENT_CHECKSUM: u8 = 0x04;
+const LZ4_FLG_DICT_ID: u8 = 0x01;
+const LZ4_BLOCK_UNCOMPRESSED: u32 = 0x8000_0000;
+
+/// 当前帧头中的校验标志
+#[derive(Clone, Copy)]
+struct Lz4FrameFlags {
+    block_checksum: bool,
+    content_checksum: bool,
+}
+
+/// `Lz4WriteDecoder` 的输入，只有 `ready` 之前（已完整到达的帧头和数据块）的字节可读
+///
+/// 解码器读到 `ready` 时得到 EOF，不会读到半个数据块；之后写入更多数据再继续读取。
+#[derive(Default)]
+struct Lz4Input {
+    data: Vec<u8>,
+    pos: usize,
+    ready: usize,
+    // None 表示下一个单元是帧头，否则是当前帧的数据块或结束标记
+    frame: Option<Lz4FrameFlags>,
+}
+
+impl Lz4Input {
+    /// 把 `ready` 推进到最后一个完整单元（帧头、数据块或结束标记）的末尾
+    fn scan(&mut self) -> io::Result<()> {
+        loop {
+            let rest = &self.data[self.ready..];
+            let u32_at = |i: usize| rest.get(i..i + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+            let (len, frame) = match self.frame {
+                None => {
+                    let (Some(magic), Some(&flg)) = (u32_at(0), rest.get(4)) else { return Ok(()) };
+                    if magic != LZ4_FRAME_MAGIC {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("不支持的 lz4 帧: {:#010x}", magic)));
+                    }
+                    let len = 7
+                        + if flg & LZ4_FLG_CONTENT_SIZE != 0 { 8 } else { 0 }
+                        + if flg & LZ4_FLG_DICT_ID != 0 { 4 } else { 0 };
+                    let flags = Lz4FrameFlags {
+                        block_checksum: flg & LZ4_FLG_BLOCK_CHECKSUM != 0,
+                        content_checksum: flg & LZ4_FLG_CONTENT_CHECKSUM != 0,
+                    };
+                    (len, Some(flags))
+                }
+                Some(flags) => {
+                    let Some(size) = u32_at(0) else { return Ok(()) };
+                    if size == 0 {
+                        // 结束标记，帧结束后下一个单元是新的帧头
+                        (4 + if flags.content_checksum { 4 } else { 0 }, None)
+                    } else {
+                        let data_len = (size & !LZ4_BLOCK_UNCOMPRESSED) as usize;
+                        (4 + data_len + if flags.block_checksum { 4 } else { 0 }, Some(flags))
+                    }
+                }
+            };
+            if rest.len() < len {
+                return Ok(());
+            }
+            self.ready += len;
+            self.frame = frame;
+        }
+    }
+
+    /// 丢弃解码器已读取的字节
+    fn compact(&mut self) {
+        self.data.drain(..self.pos);
+        self.ready -= self.pos;
+        self.pos = 0;
+    }
+}
+
+impl Read for Lz4Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = buf.len().min(self.ready - self.pos);
+        buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
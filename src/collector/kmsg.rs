@@ -0,0 +1,184 @@
+// src/collector/kmsg.rs
+// 增量读取内核日志 (/dev/kmsg)，按 boot_id 记录已读取的序列号
+//
+// 每条记录的格式: "<prio>,<seq>,<timestamp_us>,<flags>[,...];<message>"，
+// 其后可能跟随以空格开头的 KEY=value 续行。
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::error::{ClientError, Result};
+
+pub const DEFAULT_KMSG_PATH: &str = "/dev/kmsg";
+pub const DEFAULT_BOOT_ID_PATH: &str = "/proc/sys/kernel/random/boot_id";
+
+// 状态文件默认位置，与 monitor_loop.sh 的状态目录相同
+const DEFAULT_STATE_DIR: &str = ".continuous_monitor";
+const DEFAULT_STATE_FILE: &str = "kmsg_cursor.json";
+
+// 单条记录最大约 8KiB，缓冲区需能容纳一条完整记录
+const READ_BUFFER_SIZE: usize = 16 * 1024;
+
+/// 一条内核日志
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KmsgRecord {
+    pub seq: u64,
+    /// 开机以来的微秒数
+    pub timestamp_us: u64,
+    /// 日志级别 (0=emerg ... 7=debug)
+    pub priority: u8,
+    /// 日志来源 (0=kern, 1=user ...)
+    pub facility: u8,
+    pub message: String,
+}
+
+/// 读取位置，保存在状态文件中
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KmsgCursor {
+    pub boot_id: String,
+    /// 下一条要读取的序列号
+    pub next_seq: u64,
+}
+
+/// 内核日志增量读取器
+///
+/// `read_new` 只在内存中推进读取位置，调用方把记录发送（或写入 spool）成功后再用 `commit` 保存；
+/// 没有保存的记录在 `rewind` 之后或进程重启后会再次读出。
+#[derive(Debug, Clone)]
+pub struct KmsgReader {
+    kmsg_path: PathBuf,
+    boot_id_path: PathBuf,
+    state_file: PathBuf,
+    // 上次读取之后的位置，尚未保存
+    position: Option<KmsgCursor>,
+}
+
+impl KmsgReader {
+    /// 读取本机 /dev/kmsg，读取位置保存到 `state_file`
+    pub fn system(state_file: impl Into<PathBuf>) -> Self {
+        Self::new(DEFAULT_KMSG_PATH, DEFAULT_BOOT_ID_PATH, state_file)
+    }
+
+    /// 默认状态文件 $HOME/.continuous_monitor/kmsg_cursor.json
+    pub fn default_state_file() -> PathBuf {
+        let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("."));
+        home.join(DEFAULT_STATE_DIR).join(DEFAULT_STATE_FILE)
+    }
+
+    /// 指定日志来源和 boot_id 文件，可以指向录制的 kmsg 文件
+    pub fn new(kmsg_path: impl Into<PathBuf>, boot_id_path: impl Into<PathBuf>, state_file: impl Into<PathBuf>) -> Self {
+        Self {
+            kmsg_path: kmsg_path.into(),
+            boot_id_path: boot_id_path.into(),
+            state_file: state_file.into(),
+            position: None,
+        }
+    }
+
+    /// 读取上次之后的新记录，不更新状态文件；重启（boot_id 变化）后从头读取
+    ///
+    /// 从上次读取的位置继续，还没有读取过时从状态文件中保存的位置开始。
+    pub fn read_new(&mut self) -> Result<Vec<KmsgRecord>> {
+        let boot_id = fs::read_to_string(&self.boot_id_path)
+            .map(|s| s.trim().to_string())
+            .unwrap_or_else(|_| "unknown".to_string());
+
+        let mut cursor = self.position.clone().unwrap_or_else(|| self.load_cursor());
+        if cursor.boot_id != boot_id {
+            if !cursor.boot_id.is_empty() {
+                println!("[Kmsg] 检测到系统重启 (boot_id 变化)，从头读取内核日志");
+            }
+            cursor = KmsgCursor { boot_id, next_seq: 0 };
+        }
+
+        let records: Vec<KmsgRecord> = read_available(&self.kmsg_path)?
+            .lines()
+            .filter_map(parse_record)
+            .filter(|r| r.seq >= cursor.next_seq)
+            .collect();
+
+        if let Some(last) = records.last() {
+            cursor.next_seq = last.seq + 1;
+        }
+        self.position = Some(cursor);
+        Ok(records)
+    }
+
+    /// 上次 `read_new` 之后的读取位置，还没有读取过时为 None
+    pub fn position(&self) -> Option<&KmsgCursor> {
+        self.position.as_ref()
+    }
+
+    /// 保存读取位置：该位置之前的记录已经送达，之后不再读取
+    pub fn commit(&self, cursor: &KmsgCursor) -> Result<()> {
+        self.save_cursor(cursor)
+    }
+
+    /// 放弃尚未保存的读取位置，下次从状态文件中保存的位置重新读取
+    pub fn rewind(&mut self) {
+        self.position = None;
+    }
+
+    fn load_cursor(&self) -> KmsgCursor {
+        fs::read_to_string(&self.state_file)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    /// 先写临时文件再重命名，避免中途退出留下损坏的状态文件
+    fn save_cursor(&self, cursor: &KmsgCursor) -> Result<()> {
+        if let Some(dir) = self.state_file.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = self.state_file.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(cursor)?)?;
+        fs::rename(&tmp, &self.state_file)?;
+        Ok(())
+    }
+}
+
+/// 解析一行 kmsg 记录，续行和格式错误的行返回 None
+pub fn parse_record(line: &str) -> Option<KmsgRecord> {
+    if line.starts_with(' ') {
+        return None;
+    }
+    let (prefix, message) = line.split_once(';')?;
+    let mut fields = prefix.split(',');
+    let prio: u32 = fields.next()?.parse().ok()?;
+    let seq = fields.next()?.parse().ok()?;
+    let timestamp_us = fields.next()?.parse().ok()?;
+    Some(KmsgRecord {
+        seq,
+        timestamp_us,
+        priority: (prio & 0x07) as u8,
+        facility: (prio >> 3) as u8,
+        message: message.to_string(),
+    })
+}
+
+/// 非阻塞地读取当前所有可读的记录
+///
+/// /dev/kmsg 每次 read 返回一条记录，没有新记录时返回 EAGAIN；
+/// 记录被覆盖时返回 EPIPE，跳过即可。普通文件读到末尾返回 0。
+fn read_available(path: &Path) -> Result<String> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)
+        .map_err(|e| ClientError::Io(io::Error::new(e.kind(), format!("打开 {} 失败: {}", path.display(), e))))?;
+
+    let mut data = Vec::new();
+    let mut buf = vec![0u8; READ_BUFFER_SIZE];
+    loop {
+        match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => data.extend_from_slice(&buf[..n]),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe || e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(String::from_utf8_lossy(&data).into_owned())
+}
@@ -0,0 +1,148 @@
+// src/collector/mod.rs
+// 本机状态采集，替代 monitor_loop.sh 中的 top / hostname / dmesg 等外部命令
+pub mod kmsg;
+pub mod process;
+pub mod system;
+
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::error::Result;
+use crate::model::Process;
+use self::kmsg::{KmsgReader, KmsgRecord};
+use self::process::ProcessCollector;
+use self::system::CpuTimes;
+
+pub const DEFAULT_PROCFS_ROOT: &str = "/proc";
+
+/// 一次采集结果，字段与 monitor_loop.sh 生成的 JSON 一致
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SystemSnapshot {
+    pub hostname: String,
+    pub ip_address: String,
+    /// 本地时间，RFC 3339 格式
+    pub timestamp: String,
+    pub system_metrics: SystemMetrics,
+    pub logs: Logs,
+    /// 进程与线程信息，未启用进程采集时为空
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub processes: Vec<Process>,
+}
+
+/// top 格式的系统指标
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SystemMetrics {
+    pub cpu_info: String,
+    pub memory_info: String,
+    pub swap_info: String,
+    #[serde(default)]
+    pub threadinfo: String,
+    /// CPU 占用百分比，计算区间与 cpu_info 相同
+    #[serde(default)]
+    pub cpu_usage: f64,
+    /// 内存占用百分比
+    #[serde(default)]
+    pub memory_usage: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Logs {
+    /// 上次采集之后新增的内核日志
+    #[serde(default)]
+    pub dmesg: Vec<KmsgRecord>,
+}
+
+/// 系统状态采集器
+///
+/// 保存上一次的 CPU 采样，连续调用 `collect` 时 CPU 占用按两次采样之间的区间计算。
+#[derive(Debug)]
+pub struct Collector {
+    root: PathBuf,
+    prev_cpu: Option<CpuTimes>,
+    kmsg: Option<KmsgReader>,
+    processes: Option<ProcessCollector>,
+}
+
+impl Default for Collector {
+    fn default() -> Self {
+        Self::with_root(DEFAULT_PROCFS_ROOT)
+    }
+}
+
+impl Collector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 使用指定的 procfs 根目录，便于指向伪造的 procfs
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into(), prev_cpu: None, kmsg: None, processes: None }
+    }
+
+    /// 采集时同时读取新增的内核日志
+    pub fn with_kmsg(mut self, reader: KmsgReader) -> Self {
+        self.kmsg = Some(reader);
+        self
+    }
+
+    /// 采集时同时采集进程与线程信息
+    pub fn with_processes(mut self, collector: ProcessCollector) -> Self {
+        self.processes = Some(collector);
+        self
+    }
+
+    /// 内核日志读取器，用于在采样送达后保存读取位置
+    pub fn kmsg_mut(&mut self) -> Option<&mut KmsgReader> {
+        self.kmsg.as_mut()
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 采集一次系统状态
+    pub fn collect(&mut self) -> Result<SystemSnapshot> {
+        let cpu = system::read_cpu_times(&self.root)?;
+        let mem = system::read_meminfo(&self.root)?;
+        let swap = system::read_swaps(&self.root)?;
+        let cpu_info = system::format_cpu(self.prev_cpu.as_ref(), &cpu);
+        let cpu_usage = system::cpu_usage(self.prev_cpu.as_ref(), &cpu);
+        self.prev_cpu = Some(cpu);
+
+        // 主机名和地址读取失败不影响指标上报
+        let hostname = system::read_hostname(&self.root).unwrap_or_default();
+        let ip_address = system::read_ip_addresses(&self.root)
+            .ok()
+            .and_then(|ips| ips.into_iter().next())
+            .unwrap_or_default();
+
+        let processes = match &mut self.processes {
+            Some(collector) => collector.collect()?,
+            None => Vec::new(),
+        };
+
+        // 最后读取内核日志，之前的步骤失败时不推进读取位置；没有 /dev/kmsg 读取权限时只上报指标
+        let dmesg = match &mut self.kmsg {
+            Some(reader) => reader.read_new().unwrap_or_else(|e| {
+                eprintln!("[Collector] ✗ 读取内核日志失败: {}", e);
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+
+        Ok(SystemSnapshot {
+            hostname,
+            ip_address,
+            timestamp: chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%:z").to_string(),
+            system_metrics: SystemMetrics {
+                cpu_info,
+                memory_info: system::format_memory(&mem),
+                swap_info: system::format_swap(&swap, &mem),
+                threadinfo: String::new(),
+                cpu_usage,
+                memory_usage: mem.usage_percent(),
+            },
+            logs: Logs { dmesg },
+            processes,
+        })
+    }
+}
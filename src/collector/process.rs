@@ -0,0 +1,315 @@
+// src/collector/process.rs
+// 从 /proc/<pid>/task/*/stat 和 statm 采集进程与线程信息，填充 model::Process / ThreadInfo
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::error::Result;
+use crate::model::{Process, ThreadInfo, TrendPoint};
+use super::system;
+
+// 首次采集时两次采样之间的间隔
+const DEFAULT_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+// 每个进程保留的趋势点数
+const MAX_TREND_POINTS: usize = 10;
+const PASSWD_PATH: &str = "/etc/passwd";
+
+/// 进程过滤条件，未设置的条件不参与过滤
+#[derive(Debug, Clone, Default)]
+pub struct ProcessFilter {
+    /// 进程名（comm）精确匹配其中之一
+    pub names: Vec<String>,
+    /// 用户名匹配其中之一
+    pub users: Vec<String>,
+    /// 只保留 CPU 占用最高的前 N 个进程
+    pub top_n: Option<usize>,
+}
+
+/// /proc/<pid>/task/<tid>/stat 中用到的字段
+#[derive(Debug, Clone)]
+struct TaskStat {
+    comm: String,
+    state: char,
+    // utime + stime，单位 clock tick
+    cpu_ticks: u64,
+    priority: i32,
+    nice: i32,
+}
+
+/// 一次采样中的线程
+#[derive(Debug, Clone)]
+struct TaskSample {
+    tid: u32,
+    uid: u32,
+    stat: TaskStat,
+}
+
+/// 一次采样中的进程
+#[derive(Debug, Clone)]
+struct ProcessSample {
+    pid: u32,
+    uid: u32,
+    stat: TaskStat,
+    // statm 的 size / resident / shared，单位页
+    statm: [u64; 3],
+    command: String,
+    tasks: Vec<TaskSample>,
+}
+
+/// 进程与线程采集器
+///
+/// CPU 占用按两次采样之间的 clock tick 增量计算；首次调用 `collect` 时会连续采样两次。
+#[derive(Debug)]
+pub struct ProcessCollector {
+    root: PathBuf,
+    filter: ProcessFilter,
+    sample_interval: Duration,
+    clock_ticks: f64,
+    page_size: u64,
+    users: HashMap<u32, String>,
+    // 上一次采样: tid -> cpu_ticks
+    prev: Option<(Instant, HashMap<u32, u64>)>,
+    trends: HashMap<u32, Vec<TrendPoint>>,
+}
+
+impl ProcessCollector {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        // SAFETY: sysconf 只读取系统常量
+        let (clock_ticks, page_size) = unsafe { (libc::sysconf(libc::_SC_CLK_TCK), libc::sysconf(libc::_SC_PAGESIZE)) };
+        Self {
+            root: root.into(),
+            filter: ProcessFilter::default(),
+            sample_interval: DEFAULT_SAMPLE_INTERVAL,
+            clock_ticks: if clock_ticks > 0 { clock_ticks as f64 } else { 100.0 },
+            page_size: if page_size > 0 { page_size as u64 } else { 4096 },
+            users: load_users(Path::new(PASSWD_PATH)),
+            prev: None,
+            trends: HashMap::new(),
+        }
+    }
+
+    pub fn with_filter(mut self, filter: ProcessFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// 首次采集时两次采样之间的间隔
+    pub fn with_sample_interval(mut self, interval: Duration) -> Self {
+        self.sample_interval = interval;
+        self
+    }
+
+    /// 采集进程列表，CPU 占用为距上一次采集的平均值
+    pub fn collect(&mut self) -> Result<Vec<Process>> {
+        if self.prev.is_none() {
+            let samples = self.sample()?;
+            self.prev = Some((Instant::now(), tick_map(&samples)));
+            thread::sleep(self.sample_interval);
+        }
+
+        let samples = self.sample()?;
+        let now = Instant::now();
+        let (prev_at, prev_ticks) = self.prev.take().unwrap_or_else(|| (now, HashMap::new()));
+        let elapsed_ticks = now.duration_since(prev_at).as_secs_f64() * self.clock_ticks;
+        let mem_total = system::read_meminfo(&self.root)?.total;
+
+        let cpu_percent = |tid: u32, ticks: u64| -> f64 {
+            match prev_ticks.get(&tid) {
+                Some(&prev) if elapsed_ticks > 0.0 => ticks.saturating_sub(prev) as f64 * 100.0 / elapsed_ticks,
+                _ => 0.0,
+            }
+        };
+
+        let timestamp = chrono::Utc::now().timestamp_millis();
+        let mut processes: Vec<(f64, Process)> = Vec::new();
+        for sample in &samples {
+            let user_name = self.user_name(sample.uid);
+            if !self.filter.names.is_empty() && !self.filter.names.contains(&sample.stat.comm) {
+                continue;
+            }
+            if !self.filter.users.is_empty() && !self.filter.users.contains(&user_name) {
+                continue;
+            }
+
+            let [size, resident, shared] = sample.statm.map(|pages| pages * self.page_size / 1024);
+            let mem_percent = if mem_total > 0 { resident as f64 * 100.0 / mem_total as f64 } else { 0.0 };
+
+            let mut process_cpu = 0.0;
+            let threads: Vec<ThreadInfo> = sample
+                .tasks
+                .iter()
+                .map(|task| {
+                    let cpu = cpu_percent(task.tid, task.stat.cpu_ticks);
+                    process_cpu += cpu;
+                    ThreadInfo {
+                        thread_id: task.tid,
+                        user_name: self.user_name(task.uid),
+                        priority: task.stat.priority,
+                        nice_value: task.stat.nice,
+                        virtual_memory: format_kib(size),
+                        resident_memory: format_kib(resident),
+                        shared_memory: format_kib(shared),
+                        status: task.stat.state.to_string(),
+                        cpu_usage: format!("{:.1}", cpu),
+                        memory_usage: format!("{:.1}", mem_percent),
+                        runtime: format_runtime(task.stat.cpu_ticks as f64 / self.clock_ticks),
+                        command: sample.command.clone(),
+                    }
+                })
+                .collect();
+
+            let trend = self.trends.entry(sample.pid).or_default();
+            // 与 data.json 一致，最新的趋势点在前
+            trend.insert(0, TrendPoint {
+                timestamp: Some(timestamp),
+                cpu_usage: round2(process_cpu),
+                memory_usage: round2(mem_percent),
+                thread_count: threads.len() as u32,
+            });
+            trend.truncate(MAX_TREND_POINTS);
+
+            processes.push((process_cpu, Process {
+                pid: sample.pid,
+                name: sample.stat.comm.clone(),
+                user_name,
+                status: sample.stat.state.to_string(),
+                timestamp: Some(timestamp),
+                trend: trend.clone(),
+                threads,
+                ..Process::default()
+            }));
+        }
+
+        // 已退出的进程不再保留趋势
+        self.trends.retain(|pid, _| samples.iter().any(|s| s.pid == *pid));
+        self.prev = Some((now, tick_map(&samples)));
+
+        processes.sort_by(|a, b| b.0.total_cmp(&a.0));
+        if let Some(n) = self.filter.top_n {
+            processes.truncate(n);
+        }
+        Ok(processes.into_iter().map(|(_, p)| p).collect())
+    }
+
+    /// 遍历 /proc/<pid>，采集期间退出的进程直接跳过
+    fn sample(&self) -> Result<Vec<ProcessSample>> {
+        let mut samples = Vec::new();
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            let Some(pid) = entry.file_name().to_str().and_then(|s| s.parse::<u32>().ok()) else { continue };
+            if let Some(sample) = read_process(&entry.path(), pid) {
+                samples.push(sample);
+            }
+        }
+        Ok(samples)
+    }
+
+    fn user_name(&self, uid: u32) -> String {
+        self.users.get(&uid).cloned().unwrap_or_else(|| uid.to_string())
+    }
+}
+
+fn read_process(dir: &Path, pid: u32) -> Option<ProcessSample> {
+    let stat = parse_stat(&fs::read_to_string(dir.join("stat")).ok()?)?;
+    let statm = parse_statm(&fs::read_to_string(dir.join("statm")).ok()?)?;
+    let uid = read_uid(dir)?;
+
+    // 内核线程没有 cmdline，与 top 一样显示为 [comm]
+    let command = fs::read(dir.join("cmdline"))
+        .ok()
+        .map(|raw| {
+            raw.split(|b| *b == 0)
+                .filter(|part| !part.is_empty())
+                .map(|part| String::from_utf8_lossy(part).into_owned())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|cmd| !cmd.is_empty())
+        .unwrap_or_else(|| format!("[{}]", stat.comm));
+
+    let mut tasks = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir.join("task")) {
+        for entry in entries.flatten() {
+            let Some(tid) = entry.file_name().to_str().and_then(|s| s.parse::<u32>().ok()) else { continue };
+            let path = entry.path();
+            let Some(task_stat) = fs::read_to_string(path.join("stat")).ok().and_then(|s| parse_stat(&s)) else { continue };
+            tasks.push(TaskSample { tid, uid: read_uid(&path).unwrap_or(uid), stat: task_stat });
+        }
+    }
+    tasks.sort_by_key(|t| t.tid);
+
+    Some(ProcessSample { pid, uid, stat, statm, command, tasks })
+}
+
+/// 解析 stat；comm 可能包含空格和括号，以最后一个 ')' 为界
+fn parse_stat(content: &str) -> Option<TaskStat> {
+    let open = content.find('(')?;
+    let close = content.rfind(')')?;
+    let comm = content.get(open + 1..close)?.to_string();
+    // 从 state 开始的字段: state(0) ... utime(11) stime(12) ... priority(15) nice(16)
+    let fields: Vec<&str> = content.get(close + 1..)?.split_whitespace().collect();
+    let num = |i: usize| fields.get(i).and_then(|v| v.parse::<i64>().ok());
+    Some(TaskStat {
+        comm,
+        state: fields.first()?.chars().next()?,
+        cpu_ticks: (num(11)? + num(12)?) as u64,
+        priority: num(15)? as i32,
+        nice: num(16)? as i32,
+    })
+}
+
+fn parse_statm(content: &str) -> Option<[u64; 3]> {
+    let mut fields = content.split_whitespace().map(|v| v.parse::<u64>().ok());
+    Some([fields.next()??, fields.next()??, fields.next()??])
+}
+
+/// status 中 "Uid:" 行的实际 uid
+fn read_uid(dir: &Path) -> Option<u32> {
+    let status = fs::read_to_string(dir.join("status")).ok()?;
+    status
+        .lines()
+        .find_map(|l| l.strip_prefix("Uid:"))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|uid| uid.parse().ok())
+}
+
+/// 读取 passwd 中的 uid -> 用户名映射，读取失败时返回空表（显示 uid）
+fn load_users(path: &Path) -> HashMap<u32, String> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+fn tick_map(samples: &[ProcessSample]) -> HashMap<u32, u64> {
+    samples
+        .iter()
+        .flat_map(|p| p.tasks.iter().map(|t| (t.tid, t.stat.cpu_ticks)))
+        .collect()
+}
+
+/// 与报告中的格式一致: 1GiB 以上为 "1.2G"，否则为整数 MiB，如 "45M"
+fn format_kib(kib: u64) -> String {
+    if kib >= 1024 * 1024 {
+        format!("{:.1}G", kib as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{}M", kib / 1024)
+    }
+}
+
+/// CPU 时间格式化为 HH:MM:SS
+fn format_runtime(secs: f64) -> String {
+    let secs = secs as u64;
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn round2(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
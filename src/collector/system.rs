@@ -0,0 +1,213 @@
+// src/collector/system.rs
+// 从 procfs 读取 CPU / 内存 / 交换分区 / 主机名 / 地址，输出与 top 相同格式的文本
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::error::{ClientError, Result};
+
+/// /proc/stat 中 cpu 行的累计时间（单位 jiffies）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CpuTimes {
+    pub user: u64,
+    pub nice: u64,
+    pub system: u64,
+    pub idle: u64,
+    pub iowait: u64,
+    pub irq: u64,
+    pub softirq: u64,
+    pub steal: u64,
+}
+
+impl CpuTimes {
+    pub fn total(&self) -> u64 {
+        self.user + self.nice + self.system + self.idle + self.iowait + self.irq + self.softirq + self.steal
+    }
+}
+
+/// /proc/meminfo 中用到的字段（单位 KiB）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MemInfo {
+    pub total: u64,
+    pub free: u64,
+    pub available: u64,
+    pub buffers: u64,
+    pub cached: u64,
+    pub s_reclaimable: u64,
+}
+
+impl MemInfo {
+    /// 与 top 的 buff/cache 一致
+    pub fn buff_cache(&self) -> u64 {
+        self.buffers + self.cached + self.s_reclaimable
+    }
+
+    /// 与新版 procps 一致，已用 = 总量 - 可用
+    pub fn used(&self) -> u64 {
+        self.total.saturating_sub(self.available)
+    }
+
+    /// 已用内存占总量的百分比
+    pub fn usage_percent(&self) -> f64 {
+        if self.total == 0 { 0.0 } else { self.used() as f64 * 100.0 / self.total as f64 }
+    }
+}
+
+/// /proc/swaps 中所有交换分区的合计（单位 KiB）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SwapInfo {
+    pub total: u64,
+    pub used: u64,
+}
+
+/// 读取 /proc/stat 的汇总 cpu 行
+pub fn read_cpu_times(root: &Path) -> Result<CpuTimes> {
+    let path = root.join("stat");
+    let content = read_file(&path)?;
+    let line = content
+        .lines()
+        .find(|l| l.starts_with("cpu "))
+        .ok_or_else(|| invalid_data(&path, "缺少 cpu 行"))?;
+
+    let values: Vec<u64> = line.split_whitespace().skip(1).map(|v| v.parse().unwrap_or(0)).collect();
+    if values.len() < 4 {
+        return Err(invalid_data(&path, "cpu 行字段不足"));
+    }
+    let field = |i: usize| values.get(i).copied().unwrap_or(0);
+    Ok(CpuTimes {
+        user: field(0),
+        nice: field(1),
+        system: field(2),
+        idle: field(3),
+        iowait: field(4),
+        irq: field(5),
+        softirq: field(6),
+        steal: field(7),
+    })
+}
+
+/// 读取 /proc/meminfo
+pub fn read_meminfo(root: &Path) -> Result<MemInfo> {
+    let path = root.join("meminfo");
+    let content = read_file(&path)?;
+    let mut info = MemInfo::default();
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        let (Some(key), Some(value)) = (parts.next(), parts.next()) else { continue };
+        let value: u64 = value.parse().unwrap_or(0);
+        match key {
+            "MemTotal:" => info.total = value,
+            "MemFree:" => info.free = value,
+            "MemAvailable:" => info.available = value,
+            "Buffers:" => info.buffers = value,
+            "Cached:" => info.cached = value,
+            "SReclaimable:" => info.s_reclaimable = value,
+            _ => {}
+        }
+    }
+    if info.total == 0 {
+        return Err(invalid_data(&path, "缺少 MemTotal"));
+    }
+    Ok(info)
+}
+
+/// 读取 /proc/swaps，没有交换分区时返回全 0
+pub fn read_swaps(root: &Path) -> Result<SwapInfo> {
+    let content = read_file(&root.join("swaps"))?;
+    // 第一行为表头: Filename Type Size Used Priority
+    let info = content.lines().skip(1).fold(SwapInfo::default(), |mut acc, line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() >= 4 {
+            acc.total += fields[2].parse::<u64>().unwrap_or(0);
+            acc.used += fields[3].parse::<u64>().unwrap_or(0);
+        }
+        acc
+    });
+    Ok(info)
+}
+
+/// 读取主机名 (sys/kernel/hostname)
+pub fn read_hostname(root: &Path) -> Result<String> {
+    Ok(read_file(&root.join("sys/kernel/hostname"))?.trim().to_string())
+}
+
+/// 从 net/fib_trie 中取本机 IPv4 地址（排除回环地址），与 `hostname -I` 类似
+pub fn read_ip_addresses(root: &Path) -> Result<Vec<String>> {
+    let content = read_file(&root.join("net/fib_trie"))?;
+    let mut addresses: Vec<String> = Vec::new();
+    let mut last_ip: Option<&str> = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if let Some(ip) = line.strip_prefix("|-- ") {
+            last_ip = Some(ip);
+        } else if line.starts_with("/32 host LOCAL")
+            && let Some(ip) = last_ip.take()
+            && !ip.starts_with("127.")
+            && !addresses.iter().any(|a| a == ip)
+        {
+            addresses.push(ip.to_string());
+        }
+    }
+    Ok(addresses)
+}
+
+/// 生成 top 的 %Cpu(s) 行；有上一次采样时按区间计算，否则按开机以来的累计值计算
+pub fn format_cpu(prev: Option<&CpuTimes>, cur: &CpuTimes) -> String {
+    let delta = |c: u64, p: u64| c.saturating_sub(p) as f64;
+    let base = prev.copied().unwrap_or_default();
+    let total = delta(cur.total(), base.total());
+    let pct = |c: u64, p: u64| if total > 0.0 { delta(c, p) * 100.0 / total } else { 0.0 };
+    format!(
+        "%Cpu(s): {:>4.1} us, {:>4.1} sy, {:>4.1} ni, {:>4.1} id, {:>4.1} wa, {:>4.1} hi, {:>4.1} si, {:>4.1} st",
+        pct(cur.user, base.user),
+        pct(cur.system, base.system),
+        pct(cur.nice, base.nice),
+        pct(cur.idle, base.idle),
+        pct(cur.iowait, base.iowait),
+        pct(cur.irq, base.irq),
+        pct(cur.softirq, base.softirq),
+        pct(cur.steal, base.steal),
+    )
+}
+
+/// CPU 占用百分比（除 idle / iowait 以外的时间），区间的取法与 `format_cpu` 相同
+pub fn cpu_usage(prev: Option<&CpuTimes>, cur: &CpuTimes) -> f64 {
+    let base = prev.copied().unwrap_or_default();
+    let total = cur.total().saturating_sub(base.total());
+    let idle = (cur.idle + cur.iowait).saturating_sub(base.idle + base.iowait);
+    if total == 0 { 0.0 } else { total.saturating_sub(idle) as f64 * 100.0 / total as f64 }
+}
+
+/// 生成 top 的 "MiB Mem :" 行
+pub fn format_memory(mem: &MemInfo) -> String {
+    format!(
+        "MiB Mem : {:>8.1} total, {:>8.1} free, {:>8.1} used, {:>8.1} buff/cache",
+        mib(mem.total),
+        mib(mem.free),
+        mib(mem.used()),
+        mib(mem.buff_cache()),
+    )
+}
+
+/// 生成 top 的 "MiB Swap:" 行
+pub fn format_swap(swap: &SwapInfo, mem: &MemInfo) -> String {
+    format!(
+        "MiB Swap: {:>8.1} total, {:>8.1} free, {:>8.1} used. {:>8.1} avail Mem",
+        mib(swap.total),
+        mib(swap.total.saturating_sub(swap.used)),
+        mib(swap.used),
+        mib(mem.available),
+    )
+}
+
+fn mib(kib: u64) -> f64 {
+    kib as f64 / 1024.0
+}
+
+fn read_file(path: &Path) -> Result<String> {
+    fs::read_to_string(path)
+        .map_err(|e| ClientError::Io(io::Error::new(e.kind(), format!("读取 {} 失败: {}", path.display(), e))))
+}
+
+fn invalid_data(path: &Path, msg: &str) -> ClientError {
+    ClientError::Io(io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), msg)))
+}
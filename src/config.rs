@@ -0,0 +1,591 @@
+// src/config.rs
+// 客户端配置：默认值 → TOML 文件 → 环境变量，逐层覆盖
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use serde::Deserialize;
+use crate::codec::CodecId;
+use crate::data_process::ValidationMode;
+use crate::error::{ClientError, Result};
+use crate::protocol::consts::{
+    DEFAULT_PACKET_SIZE, DEFAULT_WINDOW_SIZE, MAX_MESSAGE_PACKET_SIZE, MAX_PACKET_SIZE, MAX_WINDOW_SIZE,
+    PROTOCOL_VERSION, PROTOCOL_VERSION_V1, PROTOCOL_VERSION_V2,
+};
+use crate::transport::Endpoint;
+use crate::{DEFAULT_SERVER_CID, DEFAULT_SERVER_PORT};
+
+// 环境变量名
+pub const ENV_CONFIG_FILE: &str = "XBOX_CONFIG";
+pub const ENV_ENDPOINT: &str = "XBOX_ENDPOINT";
+pub const ENV_SERVER_CID: &str = "XBOX_SERVER_CID";
+pub const ENV_SERVER_PORT: &str = "XBOX_SERVER_PORT";
+pub const ENV_PROTOCOL_VERSION: &str = "XBOX_PROTOCOL_VERSION";
+pub const ENV_CLIENT_ID: &str = "XBOX_CLIENT_ID";
+pub const ENV_MESSAGE_INTERVAL_MS: &str = "XBOX_MESSAGE_INTERVAL_MS";
+pub const ENV_COMPRESSION_LEVEL: &str = "XBOX_COMPRESSION_LEVEL";
+pub const ENV_CODEC: &str = "XBOX_CODEC";
+pub const ENV_CODEC_MIN_SIZE: &str = "XBOX_CODEC_MIN_SIZE";
+pub const ENV_WINDOW_SIZE: &str = "XBOX_WINDOW_SIZE";
+pub const ENV_MAX_PACKET_SIZE: &str = "XBOX_MAX_PACKET_SIZE";
+pub const ENV_CONNECT_TIMEOUT_MS: &str = "XBOX_CONNECT_TIMEOUT_MS";
+pub const ENV_HANDSHAKE_TIMEOUT_MS: &str = "XBOX_HANDSHAKE_TIMEOUT_MS";
+pub const ENV_ACK_TIMEOUT_MS: &str = "XBOX_ACK_TIMEOUT_MS";
+pub const ENV_TRANSFER_TIMEOUT_MS: &str = "XBOX_TRANSFER_TIMEOUT_MS";
+pub const ENV_RETRY_MAX_ATTEMPTS: &str = "XBOX_RETRY_MAX_ATTEMPTS";
+pub const ENV_RETRY_BACKOFF_MS: &str = "XBOX_RETRY_BACKOFF_MS";
+pub const ENV_RETRY_MAX_BACKOFF_MS: &str = "XBOX_RETRY_MAX_BACKOFF_MS";
+pub const ENV_RETRY_MULTIPLIER: &str = "XBOX_RETRY_MULTIPLIER";
+pub const ENV_RETRY_JITTER: &str = "XBOX_RETRY_JITTER";
+pub const ENV_VALIDATION: &str = "XBOX_VALIDATION";
+pub const ENV_SPOOL_DIR: &str = "XBOX_SPOOL_DIR";
+pub const ENV_SPOOL_MAX_BYTES: &str = "XBOX_SPOOL_MAX_BYTES";
+pub const ENV_SPOOL_MAX_AGE_SECS: &str = "XBOX_SPOOL_MAX_AGE_SECS";
+pub const ENV_SPOOL_FLUSH_INTERVAL_MS: &str = "XBOX_SPOOL_FLUSH_INTERVAL_MS";
+pub const ENV_DAEMON_SAMPLE_INTERVAL_MS: &str = "XBOX_DAEMON_SAMPLE_INTERVAL_MS";
+pub const ENV_DAEMON_BATCH_SIZE: &str = "XBOX_DAEMON_BATCH_SIZE";
+pub const ENV_DAEMON_KMSG: &str = "XBOX_DAEMON_KMSG";
+pub const ENV_DAEMON_PROCESSES: &str = "XBOX_DAEMON_PROCESSES";
+pub const ENV_DAEMON_TOP_N: &str = "XBOX_DAEMON_TOP_N";
+
+const DEFAULT_CLIENT_ID: u32 = 1;
+const DEFAULT_MESSAGE_INTERVAL_MS: u64 = 100;
+const DEFAULT_COMPRESSION_LEVEL: u32 = 6;
+const MAX_COMPRESSION_LEVEL: u32 = 9;
+
+/// 连接失败或传输中断时的重试策略（指数退避 + 随机抖动）
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// 最大尝试次数（含第一次），1 表示不重试
+    pub max_attempts: u32,
+    /// 第一次重试前的等待时间
+    pub initial_backoff: Duration,
+    /// 等待时间上限
+    pub max_backoff: Duration,
+    /// 每次重试等待时间的增长倍数
+    pub multiplier: f64,
+    /// 抖动比例 (0.0-1.0)，实际等待时间在 [d*(1-jitter), d*(1+jitter)] 内随机
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 第 `attempt` 次尝试失败后（从 1 开始）的等待时间
+    pub fn backoff_for(&self, attempt: u32) -> Duration {
+        let exp = self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        let base = (self.initial_backoff.as_secs_f64() * exp).min(self.max_backoff.as_secs_f64());
+        // 随机数映射到 [-1, 1]
+        let unit = (crate::utils::random_u64() as f64 / u64::MAX as f64) * 2.0 - 1.0;
+        let delay = base * (1.0 + self.jitter * unit);
+        Duration::from_secs_f64(delay.max(0.0))
+    }
+}
+
+/// 各阶段超时，None 表示该阶段一直阻塞
+#[derive(Debug, Clone)]
+pub struct Timeouts {
+    /// 建立连接
+    pub connect: Option<Duration>,
+    /// 发送 START 后等待服务端确认
+    pub handshake: Option<Duration>,
+    /// 传输过程中等待每个 ACK / 数据包，同时作为写超时
+    pub ack: Option<Duration>,
+    /// 从连接开始到传输结束的总时长
+    pub transfer: Option<Duration>,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: Some(Duration::from_secs(5)),
+            handshake: Some(Duration::from_secs(10)),
+            ack: Some(Duration::from_secs(30)),
+            transfer: None,
+        }
+    }
+}
+
+/// 本地 spool 设置，保存失败的报告落盘后补发
+#[derive(Debug, Clone)]
+pub struct SpoolSettings {
+    /// spool 目录，None 表示不启用
+    pub dir: Option<PathBuf>,
+    /// 总大小上限，超过后丢弃最旧的数据
+    pub max_bytes: u64,
+    /// 记录保留时长，None 表示不限
+    pub max_age: Option<Duration>,
+    /// 后台补发间隔
+    pub flush_interval: Duration,
+}
+
+impl Default for SpoolSettings {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            max_bytes: 64 * 1024 * 1024,
+            max_age: Some(Duration::from_secs(7 * 24 * 3600)),
+            flush_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// 守护进程模式的采样与发送设置
+#[derive(Debug, Clone)]
+pub struct DaemonSettings {
+    /// 采样间隔
+    pub sample_interval: Duration,
+    /// 每批包含的采样数，攒够后作为一份系统报告发送
+    pub batch_size: usize,
+    /// 是否读取新增的内核日志
+    pub kmsg: bool,
+    /// 是否采集进程与线程信息
+    pub processes: bool,
+    /// 进程只保留 CPU 占用最高的前 N 个，0 表示不限
+    pub top_n: usize,
+}
+
+impl Default for DaemonSettings {
+    fn default() -> Self {
+        Self {
+            sample_interval: Duration::from_secs(5),
+            batch_size: 12,
+            kmsg: true,
+            processes: false,
+            top_n: 10,
+        }
+    }
+}
+
+/// 客户端配置
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// 服务端地址，如 vsock://3:1234、unix:///run/xbox.sock、tcp://127.0.0.1:1234
+    pub endpoint: Endpoint,
+    /// 发送时使用的协议版本，旧版服务端设置为 1
+    pub protocol_version: u8,
+    /// 日志中标识客户端；每次传输的 message_id 由 `utils::next_message_id` 分配
+    pub client_id: u32,
+    /// dump 完成后关闭连接前的间隔
+    pub message_interval: Duration,
+    /// 压缩级别 (0-9)，zstd 中 0 表示其默认级别，lz4 / none 忽略
+    pub compression_level: u32,
+    /// 负载编码，服务端不支持时回退到服务端接受的编码
+    pub codec: CodecId,
+    /// 小于该字节数的报告不压缩，0 表示总是使用 `codec`
+    pub codec_min_size: usize,
+    /// 滑动窗口大小（未确认分片数上限，1-64），服务端可能确认更小的值
+    pub window_size: u32,
+    /// 握手时提出的最大消息包长度（消息头 + 消息体，1024-65536 字节），服务端可能确认更小的值；v1 固定为 1024
+    pub max_packet_size: u32,
+    /// 发送进程报告前的格式校验 (off / warn / reject)
+    pub validation: ValidationMode,
+    pub timeouts: Timeouts,
+    pub retry: RetryPolicy,
+    pub spool: SpoolSettings,
+    pub daemon: DaemonSettings,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: Endpoint::Vsock { cid: DEFAULT_SERVER_CID, port: DEFAULT_SERVER_PORT },
+            protocol_version: PROTOCOL_VERSION,
+            client_id: DEFAULT_CLIENT_ID,
+            message_interval: Duration::from_millis(DEFAULT_MESSAGE_INTERVAL_MS),
+            compression_level: DEFAULT_COMPRESSION_LEVEL,
+            codec: CodecId::default(),
+            codec_min_size: 0,
+            window_size: DEFAULT_WINDOW_SIZE,
+            max_packet_size: DEFAULT_PACKET_SIZE,
+            validation: ValidationMode::default(),
+            timeouts: Timeouts::default(),
+            retry: RetryPolicy::default(),
+            spool: SpoolSettings::default(),
+            daemon: DaemonSettings::default(),
+        }
+    }
+}
+
+impl ClientConfig {
+    pub fn builder() -> ClientConfigBuilder {
+        ClientConfigBuilder::default()
+    }
+
+    /// 从环境变量加载配置
+    ///
+    /// 若设置了 `XBOX_CONFIG`，先加载该 TOML 文件，再用其余环境变量覆盖。
+    pub fn from_env() -> Result<Self> {
+        Self::env_builder()?.build()
+    }
+
+    /// 与 `from_env` 相同，但返回构造器，便于调用方（如命令行参数）继续覆盖
+    pub fn env_builder() -> Result<ClientConfigBuilder> {
+        let mut builder = Self::builder();
+        if let Ok(path) = env::var(ENV_CONFIG_FILE) {
+            builder = builder.toml_file(path)?;
+        }
+        builder.env()
+    }
+
+    /// 从 TOML 文件加载配置，文件中未出现的字段使用默认值
+    pub fn from_toml_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::builder().toml_file(path)?.build()
+    }
+}
+
+/// ClientConfig 构造器
+#[derive(Debug, Clone, Default)]
+pub struct ClientConfigBuilder {
+    config: ClientConfig,
+    // cid / port 无法立即返回的错误，由 build 报告
+    error: Option<String>,
+}
+
+impl ClientConfigBuilder {
+    pub fn endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.config.endpoint = endpoint;
+        self
+    }
+
+    /// 设置 vsock 地址的 CID；当前地址不是 vsock 时 `build` 返回配置错误
+    pub fn cid(mut self, cid: u32) -> Self {
+        if let Err(e) = self.set_vsock(Some(cid), None) {
+            self.error.get_or_insert(e);
+        }
+        self
+    }
+
+    /// 设置 vsock 地址的端口；当前地址不是 vsock 时 `build` 返回配置错误
+    pub fn port(mut self, port: u32) -> Self {
+        if let Err(e) = self.set_vsock(None, Some(port)) {
+            self.error.get_or_insert(e);
+        }
+        self
+    }
+
+    pub fn protocol_version(mut self, version: u8) -> Self {
+        self.config.protocol_version = version;
+        self
+    }
+
+    pub fn client_id(mut self, client_id: u32) -> Self {
+        self.config.client_id = client_id;
+        self
+    }
+
+    pub fn message_interval(mut self, interval: Duration) -> Self {
+        self.config.message_interval = interval;
+        self
+    }
+
+    pub fn compression_level(mut self, level: u32) -> Self {
+        self.config.compression_level = level;
+        self
+    }
+
+    pub fn codec(mut self, codec: CodecId) -> Self {
+        self.config.codec = codec;
+        self
+    }
+
+    pub fn codec_min_size(mut self, size: usize) -> Self {
+        self.config.codec_min_size = size;
+        self
+    }
+
+    pub fn window_size(mut self, size: u32) -> Self {
+        self.config.window_size = size;
+        self
+    }
+
+    pub fn max_packet_size(mut self, size: u32) -> Self {
+        self.config.max_packet_size = size;
+        self
+    }
+
+    pub fn validation(mut self, mode: ValidationMode) -> Self {
+        self.config.validation = mode;
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.timeouts.connect = timeout;
+        self
+    }
+
+    pub fn handshake_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.timeouts.handshake = timeout;
+        self
+    }
+
+    pub fn ack_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.timeouts.ack = timeout;
+        self
+    }
+
+    pub fn transfer_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.timeouts.transfer = timeout;
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.config.retry = retry;
+        self
+    }
+
+    pub fn spool(mut self, spool: SpoolSettings) -> Self {
+        self.config.spool = spool;
+        self
+    }
+
+    /// 启用 spool 并设置目录
+    pub fn spool_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config.spool.dir = Some(dir.into());
+        self
+    }
+
+    pub fn daemon(mut self, daemon: DaemonSettings) -> Self {
+        self.config.daemon = daemon;
+        self
+    }
+
+    /// 用 TOML 文件中出现的字段覆盖当前配置
+    pub fn toml_file(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .map_err(|e| ClientError::Config(format!("读取配置文件 {} 失败: {}", path.display(), e)))?;
+        let file: FileConfig = toml::from_str(&content)
+            .map_err(|e| ClientError::Config(format!("解析配置文件 {} 失败: {}", path.display(), e)))?;
+
+        if let Some(v) = file.endpoint { self.config.endpoint = v.parse()?; }
+        self.set_vsock(file.server_cid, file.server_port).map_err(ClientError::Config)?;
+        let config = &mut self.config;
+        if let Some(v) = file.protocol_version { config.protocol_version = v; }
+        if let Some(v) = file.client_id { config.client_id = v; }
+        if let Some(v) = file.message_interval_ms { config.message_interval = Duration::from_millis(v); }
+        if let Some(v) = file.compression_level { config.compression_level = v; }
+        if let Some(v) = file.codec { config.codec = v.parse()?; }
+        if let Some(v) = file.codec_min_size { config.codec_min_size = v; }
+        if let Some(v) = file.window_size { config.window_size = v; }
+        if let Some(v) = file.max_packet_size { config.max_packet_size = v; }
+        if let Some(v) = file.validation { config.validation = v.parse()?; }
+        if let Some(timeouts) = file.timeouts {
+            if let Some(v) = timeouts.connect_ms { config.timeouts.connect = millis_or_none(v); }
+            if let Some(v) = timeouts.handshake_ms { config.timeouts.handshake = millis_or_none(v); }
+            if let Some(v) = timeouts.ack_ms { config.timeouts.ack = millis_or_none(v); }
+            if let Some(v) = timeouts.transfer_ms { config.timeouts.transfer = millis_or_none(v); }
+        }
+        if let Some(retry) = file.retry {
+            if let Some(v) = retry.max_attempts { config.retry.max_attempts = v; }
+            if let Some(v) = retry.initial_backoff_ms { config.retry.initial_backoff = Duration::from_millis(v); }
+            if let Some(v) = retry.max_backoff_ms { config.retry.max_backoff = Duration::from_millis(v); }
+            if let Some(v) = retry.multiplier { config.retry.multiplier = v; }
+            if let Some(v) = retry.jitter { config.retry.jitter = v; }
+        }
+        if let Some(spool) = file.spool {
+            if let Some(v) = spool.dir { config.spool.dir = Some(v); }
+            if let Some(v) = spool.max_bytes { config.spool.max_bytes = v; }
+            if let Some(v) = spool.max_age_secs { config.spool.max_age = secs_or_none(v); }
+            if let Some(v) = spool.flush_interval_ms { config.spool.flush_interval = Duration::from_millis(v); }
+        }
+        if let Some(daemon) = file.daemon {
+            if let Some(v) = daemon.sample_interval_ms { config.daemon.sample_interval = Duration::from_millis(v); }
+            if let Some(v) = daemon.batch_size { config.daemon.batch_size = v; }
+            if let Some(v) = daemon.kmsg { config.daemon.kmsg = v; }
+            if let Some(v) = daemon.processes { config.daemon.processes = v; }
+            if let Some(v) = daemon.top_n { config.daemon.top_n = v; }
+        }
+        Ok(self)
+    }
+
+    /// 用已设置的 XBOX_* 环境变量覆盖当前配置
+    pub fn env(mut self) -> Result<Self> {
+        if let Some(v) = env_var(ENV_ENDPOINT)? { self.config.endpoint = v; }
+        self.set_vsock(env_var(ENV_SERVER_CID)?, env_var(ENV_SERVER_PORT)?).map_err(ClientError::Config)?;
+        let config = &mut self.config;
+        if let Some(v) = env_var(ENV_PROTOCOL_VERSION)? { config.protocol_version = v; }
+        if let Some(v) = env_var(ENV_CLIENT_ID)? { config.client_id = v; }
+        if let Some(v) = env_var(ENV_MESSAGE_INTERVAL_MS)? { config.message_interval = Duration::from_millis(v); }
+        if let Some(v) = env_var(ENV_COMPRESSION_LEVEL)? { config.compression_level = v; }
+        if let Some(v) = env_var(ENV_CODEC)? { config.codec = v; }
+        if let Some(v) = env_var(ENV_CODEC_MIN_SIZE)? { config.codec_min_size = v; }
+        if let Some(v) = env_var(ENV_WINDOW_SIZE)? { config.window_size = v; }
+        if let Some(v) = env_var(ENV_MAX_PACKET_SIZE)? { config.max_packet_size = v; }
+        if let Some(v) = env_var(ENV_VALIDATION)? { config.validation = v; }
+        if let Some(v) = env_var(ENV_CONNECT_TIMEOUT_MS)? { config.timeouts.connect = millis_or_none(v); }
+        if let Some(v) = env_var(ENV_HANDSHAKE_TIMEOUT_MS)? { config.timeouts.handshake = millis_or_none(v); }
+        if let Some(v) = env_var(ENV_ACK_TIMEOUT_MS)? { config.timeouts.ack = millis_or_none(v); }
+        if let Some(v) = env_var(ENV_TRANSFER_TIMEOUT_MS)? { config.timeouts.transfer = millis_or_none(v); }
+        if let Some(v) = env_var(ENV_RETRY_MAX_ATTEMPTS)? { config.retry.max_attempts = v; }
+        if let Some(v) = env_var(ENV_RETRY_BACKOFF_MS)? { config.retry.initial_backoff = Duration::from_millis(v); }
+        if let Some(v) = env_var(ENV_RETRY_MAX_BACKOFF_MS)? { config.retry.max_backoff = Duration::from_millis(v); }
+        if let Some(v) = env_var(ENV_RETRY_MULTIPLIER)? { config.retry.multiplier = v; }
+        if let Some(v) = env_var(ENV_RETRY_JITTER)? { config.retry.jitter = v; }
+        if let Some(v) = env_var(ENV_SPOOL_DIR)? { config.spool.dir = Some(v); }
+        if let Some(v) = env_var(ENV_SPOOL_MAX_BYTES)? { config.spool.max_bytes = v; }
+        if let Some(v) = env_var(ENV_SPOOL_MAX_AGE_SECS)? { config.spool.max_age = secs_or_none(v); }
+        if let Some(v) = env_var(ENV_SPOOL_FLUSH_INTERVAL_MS)? { config.spool.flush_interval = Duration::from_millis(v); }
+        if let Some(v) = env_var(ENV_DAEMON_SAMPLE_INTERVAL_MS)? { config.daemon.sample_interval = Duration::from_millis(v); }
+        if let Some(v) = env_var(ENV_DAEMON_BATCH_SIZE)? { config.daemon.batch_size = v; }
+        if let Some(v) = env_var(ENV_DAEMON_KMSG)? { config.daemon.kmsg = v; }
+        if let Some(v) = env_var(ENV_DAEMON_PROCESSES)? { config.daemon.processes = v; }
+        if let Some(v) = env_var(ENV_DAEMON_TOP_N)? { config.daemon.top_n = v; }
+        Ok(self)
+    }
+
+    /// 修改 vsock 地址的 CID / 端口；当前不是 vsock 地址时返回错误，不覆盖已配置的地址
+    fn set_vsock(&mut self, cid: Option<u32>, port: Option<u32>) -> std::result::Result<(), String> {
+        if cid.is_none() && port.is_none() {
+            return Ok(());
+        }
+        let Endpoint::Vsock { cid: cur_cid, port: cur_port } = self.config.endpoint else {
+            return Err(format!("服务端地址 {} 不是 vsock 地址，不能设置 CID / 端口", self.config.endpoint));
+        };
+        self.config.endpoint = Endpoint::Vsock {
+            cid: cid.unwrap_or(cur_cid),
+            port: port.unwrap_or(cur_port),
+        };
+        Ok(())
+    }
+
+    /// 校验并生成配置
+    pub fn build(self) -> Result<ClientConfig> {
+        if let Some(e) = self.error {
+            return Err(ClientError::Config(e));
+        }
+        let config = self.config;
+        if config.protocol_version != PROTOCOL_VERSION_V1 && config.protocol_version != PROTOCOL_VERSION_V2 {
+            return Err(ClientError::Config(format!("不支持的协议版本: {}", config.protocol_version)));
+        }
+        if config.compression_level > MAX_COMPRESSION_LEVEL {
+            return Err(ClientError::Config(format!(
+                "压缩级别必须在 0-{} 之间: {}", MAX_COMPRESSION_LEVEL, config.compression_level
+            )));
+        }
+        if !(1..=MAX_WINDOW_SIZE).contains(&config.window_size) {
+            return Err(ClientError::Config(format!(
+                "窗口大小必须在 1-{} 之间: {}", MAX_WINDOW_SIZE, config.window_size
+            )));
+        }
+        if !(MAX_MESSAGE_PACKET_SIZE as u32..=MAX_PACKET_SIZE).contains(&config.max_packet_size) {
+            return Err(ClientError::Config(format!(
+                "消息包长度必须在 {}-{} 之间: {}", MAX_MESSAGE_PACKET_SIZE, MAX_PACKET_SIZE, config.max_packet_size
+            )));
+        }
+        if config.retry.max_attempts == 0 {
+            return Err(ClientError::Config("重试次数至少为 1".to_string()));
+        }
+        if config.retry.multiplier < 1.0 {
+            return Err(ClientError::Config(format!("退避倍数不能小于 1: {}", config.retry.multiplier)));
+        }
+        if !(0.0..=1.0).contains(&config.retry.jitter) {
+            return Err(ClientError::Config(format!("抖动比例必须在 0-1 之间: {}", config.retry.jitter)));
+        }
+        if config.spool.flush_interval.is_zero() {
+            return Err(ClientError::Config("spool 补发间隔必须大于 0".to_string()));
+        }
+        if config.daemon.sample_interval.is_zero() {
+            return Err(ClientError::Config("采样间隔必须大于 0".to_string()));
+        }
+        if config.daemon.batch_size == 0 {
+            return Err(ClientError::Config("每批采样数至少为 1".to_string()));
+        }
+        Ok(config)
+    }
+}
+
+/// TOML 配置文件格式，所有字段可选
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    endpoint: Option<String>,
+    server_cid: Option<u32>,
+    server_port: Option<u32>,
+    protocol_version: Option<u8>,
+    client_id: Option<u32>,
+    message_interval_ms: Option<u64>,
+    compression_level: Option<u32>,
+    codec: Option<String>,
+    codec_min_size: Option<usize>,
+    window_size: Option<u32>,
+    max_packet_size: Option<u32>,
+    validation: Option<String>,
+    timeouts: Option<FileTimeouts>,
+    retry: Option<FileRetryPolicy>,
+    spool: Option<FileSpoolSettings>,
+    daemon: Option<FileDaemonSettings>,
+}
+
+/// 超时配置，单位毫秒，0 表示不限时
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileTimeouts {
+    connect_ms: Option<u64>,
+    handshake_ms: Option<u64>,
+    ack_ms: Option<u64>,
+    transfer_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileRetryPolicy {
+    max_attempts: Option<u32>,
+    initial_backoff_ms: Option<u64>,
+    max_backoff_ms: Option<u64>,
+    multiplier: Option<f64>,
+    jitter: Option<f64>,
+}
+
+/// spool 配置，max_age_secs 为 0 表示不限
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileSpoolSettings {
+    dir: Option<PathBuf>,
+    max_bytes: Option<u64>,
+    max_age_secs: Option<u64>,
+    flush_interval_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileDaemonSettings {
+    sample_interval_ms: Option<u64>,
+    batch_size: Option<usize>,
+    kmsg: Option<bool>,
+    processes: Option<bool>,
+    top_n: Option<usize>,
+}
+
+/// 毫秒数转换为超时，0 表示不限时
+fn millis_or_none(ms: u64) -> Option<Duration> {
+    (ms > 0).then(|| Duration::from_millis(ms))
+}
+
+/// 秒数转换为时长，0 表示不限
+fn secs_or_none(secs: u64) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// 读取并解析环境变量，未设置时返回 None
+fn env_var<T: FromStr>(name: &str) -> Result<Option<T>> {
+    match env::var(name) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| ClientError::Config(format!("环境变量 {} 的值无效: {}", name, value))),
+        Err(_) => Ok(None),
+    }
+}
@@ -0,0 +1,221 @@
+// src/daemon.rs
+// 常驻模式：按计划采样，攒够一批后转换为 `model::Report` 作为系统报告保存
+//
+// SIGTERM / SIGINT: 发送已采集的数据后退出
+// SIGHUP: 发送已采集的数据，重新加载配置后继续
+// 配置了 spool 时启动后台线程补发 spool 中的记录
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+
+use crate::client::Client;
+use crate::collector::kmsg::KmsgReader;
+use crate::collector::process::{ProcessCollector, ProcessFilter};
+use crate::collector::{Collector, SystemSnapshot, DEFAULT_PROCFS_ROOT};
+use crate::config::{ClientConfig, DaemonSettings};
+use crate::constants;
+use crate::error::Result;
+use crate::model::{CrashLog, Report, Server, SystemMetric};
+use crate::spool;
+
+// 等待下一次采样时检查信号的间隔
+const SIGNAL_POLL_INTERVAL: Duration = Duration::from_millis(200);
+// 不低于该级别 (3=err) 的内核日志作为崩溃日志上报
+const CRASH_LOG_MAX_PRIORITY: u8 = 3;
+
+/// 运行守护进程直到收到 SIGTERM / SIGINT
+///
+/// `load_config` 在启动和每次收到 SIGHUP 时调用；重新加载失败时沿用旧配置。
+pub fn run<F>(mut load_config: F) -> Result<()>
+where
+    F: FnMut() -> Result<ClientConfig>,
+{
+    let terminate = Arc::new(AtomicBool::new(false));
+    let reload = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(SIGTERM, Arc::clone(&terminate))?;
+    signal_hook::flag::register(SIGINT, Arc::clone(&terminate))?;
+    signal_hook::flag::register(SIGHUP, Arc::clone(&reload))?;
+
+    let mut client = Arc::new(Client::new(load_config()?));
+    let mut flusher = start_flusher(&client);
+    let mut collector = build_collector(&client.config().daemon);
+    let mut batch: Vec<SystemSnapshot> = Vec::new();
+    let mut next_sample = Instant::now();
+    println!("[Daemon] 已启动，采样间隔 {:?}，每批 {} 个采样",
+        client.config().daemon.sample_interval, client.config().daemon.batch_size);
+
+    loop {
+        if terminate.load(Ordering::Relaxed) {
+            println!("[Daemon] 收到退出信号，发送剩余数据");
+            flush(&client, &mut collector, &mut batch);
+            stop_flusher(flusher.take());
+            break;
+        }
+
+        if reload.swap(false, Ordering::Relaxed) {
+            println!("[Daemon] 收到 SIGHUP，重新加载配置");
+            flush(&client, &mut collector, &mut batch);
+            match load_config() {
+                Ok(config) => {
+                    stop_flusher(flusher.take());
+                    client = Arc::new(Client::new(config));
+                    flusher = start_flusher(&client);
+                    collector = build_collector(&client.config().daemon);
+                    next_sample = Instant::now();
+                }
+                Err(e) => eprintln!("[Daemon] ✗ 重新加载配置失败，继续使用旧配置: {}", e),
+            }
+        }
+
+        let now = Instant::now();
+        if now >= next_sample {
+            match collector.collect() {
+                Ok(snapshot) => batch.push(snapshot),
+                Err(e) => eprintln!("[Daemon] ✗ 采样失败: {}", e),
+            }
+            // 采样耗时超过间隔时不追赶，从当前时间重新计时
+            let interval = client.config().daemon.sample_interval;
+            next_sample += interval;
+            if next_sample < Instant::now() {
+                next_sample = Instant::now() + interval;
+            }
+            if batch.len() >= client.config().daemon.batch_size {
+                flush(&client, &mut collector, &mut batch);
+            }
+            continue;
+        }
+
+        thread::sleep(SIGNAL_POLL_INTERVAL.min(next_sample - now));
+    }
+
+    println!("[Daemon] 已退出");
+    Ok(())
+}
+
+/// 启用了 spool 时启动后台补发线程
+fn start_flusher(client: &Arc<Client>) -> Option<(Arc<AtomicBool>, JoinHandle<()>)> {
+    client.spool()?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let handle = spool::spawn_flusher(Arc::clone(client), client.config().spool.flush_interval, Arc::clone(&stop));
+    Some((stop, handle))
+}
+
+fn stop_flusher(flusher: Option<(Arc<AtomicBool>, JoinHandle<()>)>) {
+    if let Some((stop, handle)) = flusher {
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join();
+    }
+}
+
+fn build_collector(settings: &DaemonSettings) -> Collector {
+    let mut collector = Collector::new();
+    if settings.kmsg {
+        collector = collector.with_kmsg(KmsgReader::system(KmsgReader::default_state_file()));
+    }
+    if settings.processes {
+        let filter = ProcessFilter {
+            top_n: (settings.top_n > 0).then_some(settings.top_n),
+            ..ProcessFilter::default()
+        };
+        collector = collector.with_processes(ProcessCollector::new(DEFAULT_PROCFS_ROOT).with_filter(filter));
+    }
+    collector
+}
+
+/// 发送当前批次，送达或写入 spool 后保存内核日志的读取位置；
+/// 都失败时丢弃该批次并回退读取位置，其中的内核日志在下一批中重新读取
+fn flush(client: &Client, collector: &mut Collector, batch: &mut Vec<SystemSnapshot>) {
+    if batch.is_empty() {
+        return;
+    }
+    let samples = std::mem::take(batch);
+    let delivered = deliver(client, &samples);
+    let Some(reader) = collector.kmsg_mut() else { return };
+    if !delivered {
+        reader.rewind();
+    } else if let Some(Err(e)) = reader.position().map(|cursor| reader.commit(cursor)) {
+        eprintln!("[Daemon] ✗ 保存内核日志读取位置失败: {}", e);
+    }
+}
+
+/// 发送一批采样；发送失败时写入 spool，由后台线程补发。返回是否已送达或写入 spool
+fn deliver(client: &Client, samples: &[SystemSnapshot]) -> bool {
+    let count = samples.len();
+    let json = match serde_json::to_string(&batch_report(samples)) {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!("[Daemon] ✗ 序列化 {} 个采样失败，已丢弃: {}", count, e);
+            return false;
+        }
+    };
+    let Err(e) = client.send_system(json.clone()) else {
+        println!("[Daemon] ✓ 已发送 {} 个采样", count);
+        return true;
+    };
+    match client.spool().map(|spool| spool.push(constants::SAVE_COMMAND, json.as_bytes())) {
+        Some(Ok(seq)) => {
+            eprintln!("[Daemon] ✗ 发送 {} 个采样失败，已写入 spool seq={}: {}", count, seq, e);
+            true
+        }
+        Some(Err(spool_err)) => {
+            eprintln!("[Daemon] ✗ 发送 {} 个采样失败且写入 spool 失败，已丢弃: {} / {}", count, e, spool_err);
+            false
+        }
+        None => {
+            eprintln!("[Daemon] ✗ 发送 {} 个采样失败，未配置 spool，已丢弃: {}", count, e);
+            false
+        }
+    }
+}
+
+/// 把一批采样转换为一份报告，`dump_reports` 可以按 `Report` 解析取回
+///
+/// 每个采样对应一条系统指标（未采集的磁盘、IO、网络指标为 0），进程取最后一次采样，
+/// err 及以上级别的内核日志作为崩溃日志。
+pub fn batch_report(samples: &[SystemSnapshot]) -> Report {
+    let Some(last) = samples.last() else { return Report::default() };
+    let round = |v: f64| (v * 100.0).round() / 100.0;
+
+    let mut system_metrics = Vec::with_capacity(samples.len());
+    let mut crash_logs = Vec::new();
+    for sample in samples {
+        let timestamp = chrono::DateTime::parse_from_rfc3339(&sample.timestamp)
+            .map(|t| t.timestamp_millis())
+            .unwrap_or_default();
+        system_metrics.push(SystemMetric {
+            timestamp,
+            cpu_usage: round(sample.system_metrics.cpu_usage),
+            memory_usage: round(sample.system_metrics.memory_usage),
+            ..SystemMetric::default()
+        });
+        for record in sample.logs.dmesg.iter().filter(|r| r.priority <= CRASH_LOG_MAX_PRIORITY) {
+            // 标题取日志来源（第一个冒号之前的部分）
+            let title = record.message.split_once(':').map_or(record.message.as_str(), |(source, _)| source);
+            crash_logs.push(CrashLog {
+                id: record.seq,
+                timestamp,
+                crash_type: "kernel".to_string(),
+                severity: if record.priority < CRASH_LOG_MAX_PRIORITY { "critical" } else { "high" }.to_string(),
+                title: title.to_string(),
+                message: record.message.clone(),
+                ..CrashLog::default()
+            });
+        }
+    }
+
+    Report {
+        servers: vec![Server {
+            server_id: last.hostname.clone(),
+            server_name: last.hostname.clone(),
+            server_ip: last.ip_address.clone(),
+            // 采集器不读取发行版信息
+            server_os: String::new(),
+            server_status: "running".to_string(),
+            system_metrics,
+            processes: last.processes.clone(),
+            crash_logs,
+        }],
+    }
+}
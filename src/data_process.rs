@@ -1,15 +1,84 @@
 // src/data_process.rs
 // 数据处理相关函数
 use std::fs;
-use std::io::{Read, Write};
-use flate2::write::ZlibEncoder;
-use flate2::read::ZlibDecoder;
+use std::str::FromStr;
+use std::sync::OnceLock;
 use flate2::Compression;
 use anyhow::Result;
 
+use crate::codec::CodecId;
+use crate::error::ClientError;
 use crate::protocol::MessagePacket;
-use crate::protocol::consts::{MSG_TYPE_DATA, MAX_MESSAGE_BODY_SIZE};
+use crate::protocol::MessageType;
+use crate::protocol::consts::MAX_MESSAGE_BODY_SIZE;
+use crate::protocol::utils as protocol_utils;
 
+/// 进程报告的 JSON Schema，格式与 test_save.json 一致
+pub const PROCESS_REPORT_SCHEMA: &str = include_str!("../schemas/process_report.schema.json");
+
+/// 发送前校验失败时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValidationMode {
+    /// 不校验
+    Off,
+    /// 打印错误后继续发送
+    #[default]
+    Warn,
+    /// 拒绝发送
+    Reject,
+}
+
+impl FromStr for ValidationMode {
+    type Err = ClientError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(ValidationMode::Off),
+            "warn" => Ok(ValidationMode::Warn),
+            "reject" => Ok(ValidationMode::Reject),
+            _ => Err(ClientError::Config(format!("未知校验模式: {} (可选 off/warn/reject)", s))),
+        }
+    }
+}
+
+/// 按进程报告格式校验 JSON 字符串，返回全部错误，为空表示通过
+pub fn validate_process_report(json: &str) -> Vec<String> {
+    static VALIDATOR: OnceLock<jsonschema::Validator> = OnceLock::new();
+
+    let instance: serde_json::Value = match serde_json::from_str(json) {
+        Ok(v) => v,
+        Err(e) => return vec![format!("不是合法的 JSON: {}", e)],
+    };
+    let validator = VALIDATOR.get_or_init(|| {
+        let schema: serde_json::Value = serde_json::from_str(PROCESS_REPORT_SCHEMA).expect("内置 schema 不是合法的 JSON");
+        jsonschema::validator_for(&schema).expect("内置 schema 无效")
+    });
+    validator
+        .iter_errors(&instance)
+        .map(|e| {
+            let path = e.instance_path().to_string();
+            format!("{}: {}", if path.is_empty() { "/" } else { &path }, e)
+        })
+        .collect()
+}
+
+/// 按校验模式处理进程报告：Warn 只打印错误，Reject 返回错误
+pub fn check_process_report(json: &str, mode: ValidationMode) -> Result<(), ClientError> {
+    if mode == ValidationMode::Off {
+        return Ok(());
+    }
+    let errors = validate_process_report(json);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    for e in &errors {
+        eprintln!("[Validate] ✗ {}", e);
+    }
+    match mode {
+        ValidationMode::Reject => Err(ClientError::Validation(format!("共 {} 处错误，首个错误: {}", errors.len(), errors[0]))),
+        _ => Ok(()),
+    }
+}
 
 /// 读取json文件并序列化为紧凑字符串
 pub fn read_json_compact(json_file_path: &str) -> Result<String> {
@@ -21,43 +90,72 @@ pub fn read_json_compact(json_file_path: &str) -> Result<String> {
 }
 
 /// 对长字符串进行压缩，返回Vec<u8>和压缩后长度
-pub fn compress_string(data: &str) -> Result<(Vec<u8>, usize)> {
-    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
-    encoder.write_all(data.as_bytes()).map_err(|e| anyhow::anyhow!("压缩写入失败: {:?}", e))?;
-    let compressed = encoder.finish().map_err(|e| anyhow::anyhow!("压缩完成失败: {:?}", e))?;
+pub fn compress_string(data: &str) -> Result<(Vec<u8>, usize), ClientError> {
+    compress_string_with_level(data, Compression::default().level())
+}
+
+/// 以指定的 zlib 压缩级别 (0-9) 压缩字符串
+pub fn compress_string_with_level(data: &str, level: u32) -> Result<(Vec<u8>, usize), ClientError> {
+    compress_with(CodecId::Zlib, data.as_bytes(), level)
+}
+
+/// 以指定编码压缩，返回Vec<u8>和压缩后长度
+pub fn compress_with(codec: CodecId, data: &[u8], level: u32) -> Result<(Vec<u8>, usize), ClientError> {
+    let compressed = codec.codec().compress(data, level)?;
     let len = compressed.len();
     Ok((compressed, len))
 }
 
 /// 对Vec<u8>数据解压，返回字符串和长度
-#[allow(dead_code)]
-pub fn decompress_to_string(data: &[u8]) -> Result<(String, usize)> {
-    let mut decoder = ZlibDecoder::new(data);
-    let mut s = String::new();
-    decoder.read_to_string(&mut s).map_err(|e| anyhow::anyhow!("解压失败: {:?}", e))?;
-    let len = s.len();  
-    Ok((s, len))
+pub fn decompress_to_string(data: &[u8]) -> Result<(String, usize), ClientError> {
+    decompress_to_string_with(CodecId::Zlib, data)
 }
 
+/// 按指定编码解压为字符串，返回字符串和长度
+pub fn decompress_to_string_with(codec: CodecId, data: &[u8]) -> Result<(String, usize), ClientError> {
+    let bytes = codec.codec().decompress(data).map_err(ClientError::Decompress)?;
+    let s = String::from_utf8(bytes)
+        .map_err(|e| ClientError::Decompress(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))?;
+    let len = s.len();
+    Ok((s, len))
+}
 
 /// 将压缩后的字节数组分片并包装为 MessagePacket 数组
+///
+/// 数组最后一个元素是 END 消息，消息体为完整负载的 SHA-256 摘要。
 pub fn wrap_message_packets(data: Vec<u8>) -> Vec<MessagePacket> {
+    wrap_message_packets_with(data, MAX_MESSAGE_BODY_SIZE)
+}
+
+/// 按握手确认的消息体上限 `max_body` 分片并包装为 MessagePacket 数组
+pub fn wrap_message_packets_with(data: Vec<u8>, max_body: usize) -> Vec<MessagePacket> {
     let total_len: usize = data.len();
     let mut packets: Vec<MessagePacket> = Vec::new();
     
     // 计算 字节数组 分片数量
-    let chunk_count = (total_len + MAX_MESSAGE_BODY_SIZE - 1) / MAX_MESSAGE_BODY_SIZE;
+    let chunk_count = total_len.div_ceil(max_body);
     for i in 0..chunk_count {
-        let start = i * MAX_MESSAGE_BODY_SIZE;
+        let start = i * max_body;
         let mut packet = MessagePacket::new(
-            MSG_TYPE_DATA,
+            MessageType::Data,
             total_len as u32,
             i as u32,
             chunk_count as u32,
         );
-        packet.from_slice(&data, start);
+        packet.from_slice(&data, start, max_body);
         packets.push(packet);
     }
+
+    // 结束消息携带完整负载摘要，供接收方在解压前校验
+    let mut end_packet = MessagePacket::new(
+        MessageType::End,
+        total_len as u32,
+        chunk_count as u32,
+        chunk_count as u32,
+    );
+    end_packet.set_body(protocol_utils::payload_digest(&data).to_vec());
+    packets.push(end_packet);
+
     packets
 }
 
@@ -0,0 +1,97 @@
+// src/error.rs
+// 对外公开的客户端错误类型
+use std::io;
+use thiserror::Error;
+use crate::codec::CodecId;
+use crate::protocol::{DecodeError, ErrorCode, ErrorReport};
+
+/// 客户端错误
+///
+/// 调用方可以据此区分“服务端不可达”“服务端拒绝”“数据损坏”等情况。
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("连接服务端失败: {0}")]
+    Connect(#[source] io::Error),
+
+    #[error("握手失败: {0}")]
+    Handshake(String),
+
+    #[error("服务端拒绝: {0}")]
+    Nack(String),
+
+    #[error("对端报告错误: {0}")]
+    Remote(ErrorReport),
+
+    #[error("服务端不接受编码 {offered}，要求使用 {accepted}")]
+    CodecRejected { offered: CodecId, accepted: CodecId },
+
+    #[error("服务端不接受消息包长度 {offered}，要求使用 {accepted}")]
+    PacketSizeRejected { offered: u32, accepted: u32 },
+
+    #[error("数据校验失败: {0}")]
+    Checksum(String),
+
+    #[error("操作超时: {0}")]
+    Timeout(String),
+
+    #[error("解压失败: {0}")]
+    Decompress(#[source] io::Error),
+
+    #[error("JSON 处理失败: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("数据格式校验失败: {0}")]
+    Validation(String),
+
+    #[error("协议错误: {0}")]
+    ProtocolViolation(String),
+
+    #[error("配置错误: {0}")]
+    Config(String),
+
+    #[error("I/O 错误: {0}")]
+    Io(#[source] io::Error),
+}
+
+impl From<io::Error> for ClientError {
+    fn from(e: io::Error) -> Self {
+        // 设置了读写超时的 socket 超时后返回 WouldBlock / TimedOut
+        if crate::protocol::frame::is_timeout(&e) {
+            ClientError::Timeout(e.to_string())
+        } else {
+            ClientError::Io(e)
+        }
+    }
+}
+
+impl ClientError {
+    /// 重新连接后可能成功的错误（连接失败、超时、连接中断、服务端存储暂时不可用）
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Connect(_) | ClientError::Timeout(_) | ClientError::Io(_) => true,
+            ClientError::Remote(report) => report.code.is_transient(),
+            _ => false,
+        }
+    }
+
+    /// 中止传输时发给对端的 ERROR 报告
+    pub fn to_report(&self) -> ErrorReport {
+        match self {
+            ClientError::Checksum(reason) => ErrorReport::new(ErrorCode::Checksum, reason.clone()),
+            ClientError::ProtocolViolation(reason) => ErrorReport::new(ErrorCode::Protocol, reason.clone()),
+            ClientError::Decompress(_) => ErrorReport::new(ErrorCode::Protocol, self.to_string()),
+            _ => ErrorReport::new(ErrorCode::Internal, self.to_string()),
+        }
+    }
+}
+
+impl From<DecodeError> for ClientError {
+    fn from(e: DecodeError) -> Self {
+        match e {
+            DecodeError::Checksum { .. } => ClientError::Checksum(e.to_string()),
+            _ => ClientError::ProtocolViolation(e.to_string()),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
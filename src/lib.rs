@@ -4,10 +4,26 @@ pub mod utils;
 pub mod constants;
 pub mod protocol;
 pub mod data_process;
+pub mod error;
+pub mod config;
+pub mod client;
+pub mod transport;
+pub mod server;
+pub mod model;
+pub mod collector;
+pub mod daemon;
+pub mod spool;
+pub mod codec;
+pub mod mux;
 
 use std::sync::Mutex;
-use anyhow::Result;
-use crate::protocol::MessagePacket;
+
+pub use crate::error::{ClientError, Result};
+pub use crate::config::{ClientConfig, ClientConfigBuilder, DaemonSettings, RetryPolicy, SpoolSettings};
+pub use crate::client::Client;
+pub use crate::transport::{Endpoint, Transport};
+pub use crate::model::Report;
+pub use crate::codec::CodecId;
 
 // 全局互斥锁，用于保护 Vsock 通信不被并发竞争
 static VSOCK_MUTEX: Mutex<()> = Mutex::new(());
@@ -15,37 +31,32 @@ static VSOCK_MUTEX: Mutex<()> = Mutex::new(());
 pub const DEFAULT_SERVER_CID: u32 = 3;  // 默认连接 Host (CID=3)
 pub const DEFAULT_SERVER_PORT: u32 = 1234;
 
+/// 使用环境变量中的配置（未设置时为默认值）保存进程报告
 pub fn send_process(message_str: String) -> Result<()> {
-
-    // 压缩字符串
-    let (compressed_data, compressed_len) = data_process::compress_string(&message_str)?;
-    println!("字符串压缩前后长度是：{}-->{}", message_str.len(), compressed_len);
-
-    // 将压缩字符串包装成 message_packet 数组
-    let mut msg_packets: Vec<MessagePacket>  = data_process::wrap_message_packets(compressed_data);
-
-    for packet in &mut msg_packets {
-            packet.header.set_message_id(1);
-    }
-
-    // 获取锁，保护通信过程
-    {
-        let _guard = VSOCK_MUTEX.lock().map_err(|e| anyhow::anyhow!("获取锁失败: {:?}", e))?;
-        client_thread_save::client_thread(msg_packets, DEFAULT_SERVER_CID, DEFAULT_SERVER_PORT, constants::SAVE_PROCESS_COMMAND).unwrap_or_else(|e| {
-                eprintln!("Save 线程 出现错误: {:?}", e);
-        });
-    }
-   
-    Ok(())
+    Client::new(ClientConfig::from_env()?).send_process(message_str)
 }
 
+/// 使用环境变量中的配置（未设置时为默认值）取回进程报告
 pub fn dump_process() -> Result<Vec<u8>> {
-    // 获取锁，保护通信过程
-    let _guard = VSOCK_MUTEX.lock().map_err(|e| anyhow::anyhow!("获取锁失败: {:?}", e))?;
+    Client::new(ClientConfig::from_env()?).dump_process()
+}
+
+/// 使用环境变量中的配置（未设置时为默认值）保存系统报告
+pub fn send_system(message_str: String) -> Result<()> {
+    Client::new(ClientConfig::from_env()?).send_system(message_str)
+}
+
+/// 使用环境变量中的配置（未设置时为默认值）取回系统报告
+pub fn dump_system() -> Result<Vec<u8>> {
+    Client::new(ClientConfig::from_env()?).dump_system()
+}
 
-    let ret = client_thread_dump::client_thread(DEFAULT_SERVER_CID, DEFAULT_SERVER_PORT, constants::DUMP_PROCESS_COMMAND).unwrap_or_else(|e| {
-        Vec::new()
-    });
-    Ok(ret)
+/// 使用环境变量中的配置（未设置时为默认值）保存结构化报告
+pub fn save_report(report: &Report) -> Result<()> {
+    Client::new(ClientConfig::from_env()?).save_report(report)
+}
 
+/// 使用环境变量中的配置（未设置时为默认值）取回结构化报告
+pub fn dump_reports() -> Result<Vec<Report>> {
+    Client::new(ClientConfig::from_env()?).dump_reports()
 }
@@ -0,0 +1,138 @@
+// src/model.rs
+// 报告数据模型，字段与 data.json / echo.json / test_save.json 中的 JSON 保持一致（camelCase）
+use serde::{Deserialize, Serialize};
+
+/// 一份完整的报告
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    #[serde(default)]
+    pub servers: Vec<Server>,
+}
+
+/// 单台服务器的状态
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Server {
+    pub server_id: String,
+    pub server_name: String,
+    pub server_ip: String,
+    pub server_os: String,
+    pub server_status: String,
+    #[serde(default)]
+    pub system_metrics: Vec<SystemMetric>,
+    #[serde(default)]
+    pub processes: Vec<Process>,
+    #[serde(default)]
+    pub crash_logs: Vec<CrashLog>,
+}
+
+/// 系统指标采样
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemMetric {
+    // 单独上报（不在 Server 下）时携带所属服务器
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_id: Option<String>,
+    pub timestamp: i64,
+    pub cpu_usage: f64,
+    pub memory_usage: f64,
+    pub disk_usage: f64,
+    pub io_read: f64,
+    pub io_write: f64,
+    pub network_in: f64,
+    pub network_out: f64,
+}
+
+/// 进程信息
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Process {
+    pub pid: u32,
+    pub name: String,
+    pub user_name: String,
+    pub status: String,
+    // 单独上报（不在 Server 下）时携带所属服务器和采样时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_ip: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_os: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_status: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+    #[serde(default)]
+    pub trend: Vec<TrendPoint>,
+    #[serde(default)]
+    pub threads: Vec<ThreadInfo>,
+}
+
+/// 进程资源占用趋势
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrendPoint {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+    pub cpu_usage: f64,
+    pub memory_usage: f64,
+    pub thread_count: u32,
+}
+
+/// 线程信息，数值沿用 top 的文本格式（如 "1.2G"、"00:15:32"）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadInfo {
+    pub thread_id: u32,
+    pub user_name: String,
+    pub priority: i32,
+    pub nice_value: i32,
+    pub virtual_memory: String,
+    pub resident_memory: String,
+    pub shared_memory: String,
+    pub status: String,
+    pub cpu_usage: String,
+    pub memory_usage: String,
+    pub runtime: String,
+    pub command: String,
+}
+
+/// 崩溃日志
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashLog {
+    pub id: u64,
+    pub timestamp: i64,
+    pub crash_type: String,
+    pub severity: String,
+    pub title: String,
+    pub message: String,
+    pub stack_trace: String,
+    #[serde(default)]
+    pub resolved: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ai_suggestion: Option<AiSuggestion>,
+}
+
+/// 崩溃日志的 AI 分析建议
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AiSuggestion {
+    pub summary: String,
+    pub analysis: String,
+    #[serde(default)]
+    pub recommendations: Vec<Recommendation>,
+}
+
+/// 单条处理建议
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recommendation {
+    pub priority: u32,
+    pub action: String,
+    #[serde(default)]
+    pub command: String,
+}
@@ -0,0 +1,196 @@
+// src/mux.rs
+// 多路复用：在一条连接上交错发送多份报告，ACK / ERROR 按 message_id 分发给对应的传输
+use std::collections::HashMap;
+use std::io::Write;
+use crate::codec::CodecId;
+use crate::config::ClientConfig;
+use crate::data_process;
+use crate::error::{ClientError, Result};
+use crate::protocol::consts::PROTOCOL_VERSION_V2;
+use crate::protocol::{control, ErrorReport, FramedStream, MessagePacket, MessageType, StartRequest, Window, WindowSender};
+use crate::protocol::utils as protocol_utils;
+use crate::transport::Transport;
+use crate::utils;
+
+/// 单份报告的传输阶段
+enum State {
+    /// 已发送 START，等待服务端确认
+    Handshake,
+    /// 按窗口发送 DATA
+    Sending(WindowSender),
+    /// DATA 全部确认，已发送 END，等待最后的 ACK
+    Ending,
+}
+
+/// 一份报告：待发送的消息包（最后一个为 END）和发送进度
+struct Transfer {
+    request: StartRequest,
+    packets: Vec<MessagePacket>,
+    next: usize,
+    state: State,
+}
+
+/// 多路复用发送器
+///
+/// 每份报告分配独立的 message_id；各报告的窗口未满时轮流发送一个分片，
+/// 全部窗口都满时读取一个 ACK 交给对应的传输。
+/// 需要协议 v2 和支持多路复用的服务端；不续传，单份报告失败不影响其他报告。
+pub struct Multiplexer<T: Transport = Box<dyn Transport>> {
+    stream: FramedStream<T>,
+    window: u32,
+    max_packet_size: u32,
+    transfers: HashMap<u32, Transfer>,
+    // 按 start 的顺序记录 message_id，结果按该顺序返回
+    order: Vec<u32>,
+    results: HashMap<u32, Result<()>>,
+}
+
+impl Multiplexer {
+    /// 按配置连接服务端
+    pub fn connect(config: &ClientConfig) -> Result<Self> {
+        if config.protocol_version != PROTOCOL_VERSION_V2 {
+            return Err(ClientError::Config("多路复用需要协议 v2".to_string()));
+        }
+        let mut stream = utils::connect(config, &format!("[Mux-{}]", config.client_id))?;
+        stream.set_read_timeout(config.timeouts.ack);
+        Ok(Self::new(stream, config.window_size, config.max_packet_size))
+    }
+}
+
+impl<T: Transport> Multiplexer<T> {
+    pub fn new(stream: FramedStream<T>, window: u32, max_packet_size: u32) -> Self {
+        Self {
+            stream,
+            window,
+            max_packet_size,
+            transfers: HashMap::new(),
+            order: Vec::new(),
+            results: HashMap::new(),
+        }
+    }
+
+    /// 加入一份已按 `codec` 压缩的报告并发送 START，返回分配的 message_id
+    pub fn start(&mut self, command: u8, codec: CodecId, data: &[u8]) -> Result<u32> {
+        let msg_id = utils::next_message_id();
+        let mut packets = data_process::wrap_message_packets_with(data.to_vec(), control::max_body_size(self.max_packet_size));
+        for packet in &mut packets {
+            packet.header.set_message_id(msg_id);
+            packet.header.set_reserved(codec.into());
+        }
+
+        let request = StartRequest {
+            transfer_id: 0,
+            total_size: data.len() as u32,
+            chunk_count: packets.len() as u32 - 1,
+            codec,
+            window: self.window,
+            max_packet_size: self.max_packet_size,
+        };
+        protocol_utils::send_start_message(&mut self.stream, msg_id, command, &request)?;
+        println!("[Mux] 开始传输 message_id={:#x}，{} 个分片", msg_id, request.chunk_count);
+
+        self.transfers.insert(msg_id, Transfer { request, packets, next: 0, state: State::Handshake });
+        self.order.push(msg_id);
+        Ok(msg_id)
+    }
+
+    /// 交错发送全部报告，直到每一份都被确认或失败，然后关闭连接
+    ///
+    /// 返回 (message_id, 结果)，顺序与 `start` 的调用顺序一致；连接失败时返回错误。
+    pub fn finish(mut self) -> Result<Vec<(u32, Result<()>)>> {
+        let result = self.run();
+        utils::graceful_shutdown(self.stream.get_mut(), "[Mux]");
+        result?;
+
+        let mut results = self.results;
+        Ok(self
+            .order
+            .into_iter()
+            .map(|id| (id, results.remove(&id).unwrap_or(Ok(()))))
+            .collect())
+    }
+
+    fn run(&mut self) -> Result<()> {
+        while !self.transfers.is_empty() {
+            if !self.send_ready()? {
+                self.stream.flush()?;
+                let packet = self.stream.read_packet()?;
+                self.dispatch(packet)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 每个窗口未满的传输发送一个分片，DATA 全部确认的传输发送 END，返回是否发送了消息
+    fn send_ready(&mut self) -> Result<bool> {
+        let mut sent = false;
+        for transfer in self.transfers.values_mut() {
+            let State::Sending(sender) = &mut transfer.state else { continue };
+            let end = transfer.packets.len() - 1;
+            if transfer.next < end {
+                if sender.can_send() {
+                    sender.send(&mut self.stream, transfer.packets[transfer.next].clone())?;
+                    transfer.next += 1;
+                    sent = true;
+                }
+            } else if sender.in_flight() == 0 {
+                protocol_utils::send_data_message(&mut self.stream, &transfer.packets[end])?;
+                transfer.state = State::Ending;
+                sent = true;
+            }
+        }
+        Ok(sent)
+    }
+
+    /// 把一个 ACK / ERROR 交给对应的传输；已经结束的传输的消息直接忽略
+    fn dispatch(&mut self, packet: MessagePacket) -> Result<()> {
+        let msg_id = packet.header.message_id;
+        let Some(transfer) = self.transfers.get_mut(&msg_id) else { return Ok(()) };
+
+        let outcome = match packet.header.msg_type {
+            MessageType::Error => {
+                Some(Err(ClientError::Remote(ErrorReport::from_packet(packet.header.chunk_index, &packet.body))))
+            }
+            MessageType::Ack => match &mut transfer.state {
+                State::Handshake => match protocol_utils::accept_start_ack(&mut self.stream, &transfer.request, &packet) {
+                    Ok(ack) if ack.codec != transfer.request.codec => {
+                        Some(Err(ClientError::CodecRejected { offered: transfer.request.codec, accepted: ack.codec }))
+                    }
+                    Ok(ack) if ack.max_packet_size != transfer.request.max_packet_size => {
+                        Some(Err(ClientError::PacketSizeRejected {
+                            offered: transfer.request.max_packet_size,
+                            accepted: ack.max_packet_size,
+                        }))
+                    }
+                    Ok(ack) => {
+                        let window = Window::negotiated(ack.window, 1);
+                        transfer.state = State::Sending(WindowSender::new(window, msg_id, ack.resume_from));
+                        transfer.next = ack.resume_from as usize;
+                        None
+                    }
+                    Err(e) => Some(Err(e)),
+                },
+                State::Sending(sender) => sender.handle_ack(&mut self.stream, &packet).err().map(Err),
+                State::Ending => Some(Ok(())),
+            },
+            other => Some(Err(ClientError::ProtocolViolation(format!("收到非预期消息: {}", other)))),
+        };
+
+        let Some(result) = outcome else { return Ok(()) };
+        self.transfers.remove(&msg_id);
+        match &result {
+            Ok(()) => println!("[Mux] ✓ message_id={:#x} 传输完成", msg_id),
+            // 连接已不可用，其余传输也无法继续
+            Err(ClientError::Io(_) | ClientError::Timeout(_)) => return result,
+            Err(e) => {
+                eprintln!("[Mux] ✗ message_id={:#x} 传输失败: {}", msg_id, e);
+                // 服务端报告的错误无需回复，本端中止的传输通知服务端
+                if !matches!(e, ClientError::Remote(_)) {
+                    protocol_utils::send_error_message(&mut self.stream, msg_id, &e.to_report())?;
+                }
+            }
+        }
+        self.results.insert(msg_id, result);
+        Ok(())
+    }
+}
@@ -1,14 +1,45 @@
-#[allow(dead_code)]
+// 未协商消息包长度时（旧版对端）使用的消息包长度
 pub const MAX_MESSAGE_PACKET_SIZE: usize = 1024;
-pub const MESSAGE_HEADER_SIZE: usize = 20;
+// v1 消息头: 20 字节，无消息体长度字段，校验和 1 字节
+pub const MESSAGE_HEADER_SIZE_V1: usize = 20;
+// v2 消息头: 28 字节，增加 4 字节消息体长度，校验和扩展为 4 字节
+pub const MESSAGE_HEADER_SIZE_V2: usize = 28;
+// 未协商时每个分片的消息体长度，v1 接收方据此推导消息体长度
 pub const MAX_MESSAGE_BODY_SIZE: usize = 1004;
+// 握手可协商的消息包长度（消息头 + 消息体）上限，客户端默认提出该值
+pub const MAX_PACKET_SIZE: u32 = 64 * 1024;
+pub const DEFAULT_PACKET_SIZE: u32 = MAX_PACKET_SIZE;
+// END 消息体携带的完整负载 SHA-256 摘要长度 (仅 v2)
+pub const PAYLOAD_DIGEST_SIZE: usize = 32;
 
-pub const PROTOCOL_VERSION: u8 = 1;
+pub const PROTOCOL_VERSION_V1: u8 = 1;
+pub const PROTOCOL_VERSION_V2: u8 = 2;
+pub const PROTOCOL_VERSION: u8 = PROTOCOL_VERSION_V2;
 
 pub const MSG_TYPE_START: u8 = 0x01;
 pub const MSG_TYPE_DATA: u8 = 0x02;
 pub const MSG_TYPE_END: u8 = 0x03;
 pub const MSG_TYPE_ACK: u8 = 0x04;
-#[allow(dead_code)]
 pub const MSG_TYPE_ERROR: u8 = 0x05;
-pub const MSG_TYPE_ALL_END: u8 = 0x06;
\ No newline at end of file
+pub const MSG_TYPE_ALL_END: u8 = 0x06;
+
+// 滑动窗口：START 中提出、ACK 中确认的窗口大小（未确认分片数上限）
+pub const DEFAULT_WINDOW_SIZE: u32 = 16;
+pub const MAX_WINDOW_SIZE: u32 = 64;
+// 不支持滑动窗口的旧版对端：dump 时客户端每收到 5 个分片回复一次 ACK
+pub const LEGACY_DUMP_ACK_INTERVAL: u32 = 5;
+// ACK 的 reserved 字段：请求重传 chunk_count 指定的分片（校验失败）
+pub const ACK_FLAG_RETRANSMIT: u8 = 0x01;
+// 一次传输中允许的重传次数，超过后视为链路异常
+pub const MAX_RETRANSMITS: u32 = 16;
+
+// ERROR 消息头 chunk_index 携带的错误码，消息体为 UTF-8 原因（仅 v2）
+pub const ERROR_CODE_CHECKSUM: u32 = 1;
+pub const ERROR_CODE_PROTOCOL: u32 = 2;
+pub const ERROR_CODE_UNKNOWN_COMMAND: u32 = 3;
+pub const ERROR_CODE_STORAGE_FULL: u32 = 4;
+pub const ERROR_CODE_STORAGE: u32 = 5;
+pub const ERROR_CODE_INTERNAL: u32 = 6;
+pub const ERROR_CODE_TOO_LARGE: u32 = 7;
+// ERROR 消息中原因的最大长度，超出部分截断
+pub const MAX_ERROR_REASON_SIZE: usize = 512;
@@ -0,0 +1,211 @@
+// src/protocol/control.rs
+// 控制消息（START / ACK / ERROR 等）携带的参数
+use std::fmt;
+use crate::codec::CodecId;
+use super::consts::*;
+
+/// START 消息携带的参数
+///
+/// `total_size` / `chunk_count` 写入消息头，`transfer_id`、`codec`、`window` 和 `max_packet_size` 写入消息体（仅 v2）。
+/// transfer_id 为 0 表示不需要续传。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StartRequest {
+    pub transfer_id: u64,
+    pub total_size: u32,
+    pub chunk_count: u32,
+    /// 客户端提出的负载编码
+    pub codec: CodecId,
+    /// 客户端提出的窗口大小，0 表示不使用滑动窗口
+    pub window: u32,
+    /// 客户端提出的最大消息包长度（消息头 + 消息体），0 表示使用固定的分片长度
+    pub max_packet_size: u32,
+}
+
+impl StartRequest {
+    /// START 消息体编码: transfer_id(8) | codec(1) | window(4) | max_packet_size(4)
+    pub fn body_bytes(&self) -> Vec<u8> {
+        let mut body = self.transfer_id.to_be_bytes().to_vec();
+        body.push(self.codec.into());
+        body.extend_from_slice(&self.window.to_be_bytes());
+        body.extend_from_slice(&self.max_packet_size.to_be_bytes());
+        body
+    }
+
+    /// 从 START 消息解析参数
+    ///
+    /// 消息体缺失（v1 客户端）时 transfer_id 为 0；旧版客户端不携带编码，视为 zlib；
+    /// 不携带窗口大小、消息包长度时为 0。未知的编码编号同样按 zlib 处理，服务端在 ACK 中回复实际接受的编码。
+    pub fn from_packet(total_size: u32, chunk_count: u32, body: &[u8]) -> Self {
+        let transfer_id = body
+            .get(..8)
+            .and_then(|b| b.try_into().ok())
+            .map(u64::from_be_bytes)
+            .unwrap_or(0);
+        let codec = body
+            .get(8)
+            .and_then(|b| CodecId::try_from(*b).ok())
+            .unwrap_or_default();
+        let window = read_u32(body, 9);
+        let max_packet_size = read_u32(body, 13);
+        Self { transfer_id, total_size, chunk_count, codec, window, max_packet_size }
+    }
+}
+
+/// 服务端对 START 的确认
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StartAck {
+    /// 服务端已确认的分片数（续传起点），新传输为 0
+    pub resume_from: u32,
+    /// 服务端接受的负载编码，写在 ACK 消息体中；旧版服务端不携带，视为 zlib
+    pub codec: CodecId,
+    /// 服务端确认的窗口大小；0 表示不使用滑动窗口（旧版服务端不携带）
+    pub window: u32,
+    /// 服务端确认的最大消息包长度，不超过客户端提出的值；0 表示使用固定的分片长度（旧版服务端不携带）
+    pub max_packet_size: u32,
+}
+
+impl StartAck {
+    /// ACK 消息体编码: codec(1) | window(4) | max_packet_size(4)
+    pub fn body_bytes(&self) -> Vec<u8> {
+        let mut body = vec![self.codec.into()];
+        body.extend_from_slice(&self.window.to_be_bytes());
+        body.extend_from_slice(&self.max_packet_size.to_be_bytes());
+        body
+    }
+
+    /// 本次传输每个分片的消息体上限
+    pub fn max_body_size(&self) -> usize {
+        max_body_size(self.max_packet_size)
+    }
+
+    pub fn from_packet(chunk_index: u32, body: &[u8]) -> Self {
+        let codec = body
+            .first()
+            .and_then(|b| CodecId::try_from(*b).ok())
+            .unwrap_or_default();
+        Self { resume_from: chunk_index, codec, window: read_u32(body, 1), max_packet_size: read_u32(body, 5) }
+    }
+}
+
+/// ERROR 消息携带的错误码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// 数据校验失败（校验和或负载摘要不匹配）
+    Checksum,
+    /// 消息格式或对话流程错误
+    Protocol,
+    /// 不支持的命令
+    UnknownCommand,
+    /// 服务端存储空间不足
+    StorageFull,
+    /// 服务端存储读写失败
+    Storage,
+    /// 其他内部错误
+    Internal,
+    /// 报告超过服务端允许的大小
+    TooLarge,
+    /// 新版对端定义、本端不认识的错误码
+    Other(u32),
+}
+
+impl ErrorCode {
+    /// 稍后重新发送可能成功的错误（服务端存储暂时不可用）
+    pub fn is_transient(&self) -> bool {
+        matches!(self, ErrorCode::StorageFull | ErrorCode::Storage)
+    }
+}
+
+impl From<u32> for ErrorCode {
+    fn from(code: u32) -> Self {
+        match code {
+            ERROR_CODE_CHECKSUM => ErrorCode::Checksum,
+            ERROR_CODE_PROTOCOL => ErrorCode::Protocol,
+            ERROR_CODE_UNKNOWN_COMMAND => ErrorCode::UnknownCommand,
+            ERROR_CODE_STORAGE_FULL => ErrorCode::StorageFull,
+            ERROR_CODE_STORAGE => ErrorCode::Storage,
+            ERROR_CODE_INTERNAL => ErrorCode::Internal,
+            ERROR_CODE_TOO_LARGE => ErrorCode::TooLarge,
+            other => ErrorCode::Other(other),
+        }
+    }
+}
+
+impl From<ErrorCode> for u32 {
+    fn from(code: ErrorCode) -> u32 {
+        match code {
+            ErrorCode::Checksum => ERROR_CODE_CHECKSUM,
+            ErrorCode::Protocol => ERROR_CODE_PROTOCOL,
+            ErrorCode::UnknownCommand => ERROR_CODE_UNKNOWN_COMMAND,
+            ErrorCode::StorageFull => ERROR_CODE_STORAGE_FULL,
+            ErrorCode::Storage => ERROR_CODE_STORAGE,
+            ErrorCode::Internal => ERROR_CODE_INTERNAL,
+            ErrorCode::TooLarge => ERROR_CODE_TOO_LARGE,
+            ErrorCode::Other(code) => code,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ErrorCode::Checksum => "数据校验失败",
+            ErrorCode::Protocol => "协议错误",
+            ErrorCode::UnknownCommand => "不支持的命令",
+            ErrorCode::StorageFull => "存储空间不足",
+            ErrorCode::Storage => "存储读写失败",
+            ErrorCode::Internal => "内部错误",
+            ErrorCode::TooLarge => "报告过大",
+            ErrorCode::Other(code) => return write!(f, "未知错误 {}", code),
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// ERROR 消息携带的错误报告
+///
+/// 错误码写入消息头的 chunk_index，原因写入消息体（仅 v2，v1 只能携带错误码）。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorReport {
+    pub code: ErrorCode,
+    pub reason: String,
+}
+
+impl ErrorReport {
+    pub fn new(code: ErrorCode, reason: impl Into<String>) -> Self {
+        Self { code, reason: reason.into() }
+    }
+
+    pub fn from_packet(chunk_index: u32, body: &[u8]) -> Self {
+        Self { code: ErrorCode::from(chunk_index), reason: String::from_utf8_lossy(body).into_owned() }
+    }
+}
+
+impl std::error::Error for ErrorReport {}
+
+impl fmt::Display for ErrorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.reason.is_empty() {
+            write!(f, "{}", self.code)
+        } else {
+            write!(f, "{}: {}", self.code, self.reason)
+        }
+    }
+}
+
+/// 消息体中 offset 处的 u32，不足 4 字节时为 0
+fn read_u32(body: &[u8], offset: usize) -> u32 {
+    body.get(offset..offset + 4)
+        .and_then(|b| b.try_into().ok())
+        .map(u32::from_be_bytes)
+        .unwrap_or(0)
+}
+
+/// 消息包长度对应的消息体上限
+///
+/// 0 表示未协商，使用 `MAX_MESSAGE_BODY_SIZE`（v1 接收方按该长度推导消息体长度）。
+pub fn max_body_size(max_packet_size: u32) -> usize {
+    match max_packet_size {
+        0 => MAX_MESSAGE_BODY_SIZE,
+        size => (size as usize).saturating_sub(MESSAGE_HEADER_SIZE_V2),
+    }
+}
@@ -0,0 +1,26 @@
+// src/protocol/error.rs
+use thiserror::Error;
+
+/// 消息包解码错误
+///
+/// 对端发来的任何字节都不应导致进程 panic，解码失败统一返回该错误。
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DecodeError {
+    #[error("消息头长度不足: 期望 {expected} 字节, 实际 {actual} 字节")]
+    ShortHeader { expected: usize, actual: usize },
+
+    #[error("未知的消息类型: {0:#04x}")]
+    UnknownType(u8),
+
+    #[error("不支持的协议版本: {0}")]
+    BadVersion(u8),
+
+    #[error("消息长度不匹配: 期望 {expected} 字节, 实际 {actual} 字节")]
+    LengthMismatch { expected: usize, actual: usize },
+
+    #[error("分片索引越界: index={chunk_index}, total_size={total_size}")]
+    ChunkOutOfRange { chunk_index: u32, total_size: u32 },
+
+    #[error("分片 {chunk_index} 校验和不匹配: 期望 {expected:#x}, 实际 {actual:#x}")]
+    Checksum { chunk_index: u32, expected: u32, actual: u32 },
+}
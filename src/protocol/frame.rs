@@ -0,0 +1,176 @@
+// src/protocol/frame.rs
+// 基于长度的分帧读取：保证每次交付给上层的都是一个完整的消息包
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+use crate::error::{ClientError, Result};
+use crate::transport::Transport;
+use crate::protocol::message::MessagePacket;
+use crate::protocol::msg_header::MessageHeader;
+use crate::protocol::error::DecodeError;
+use crate::protocol::consts::*;
+
+/// 带接收缓冲的消息流
+///
+/// 一次 `read()` 可能只返回半个消息包，也可能同时返回多个消息包。
+/// `FramedStream` 先读满消息头，再根据消息头推导出消息体长度并读满消息体，
+/// 多读到的字节暂存在 `pending` 中，留给下一次 `read_packet` 使用。
+/// 握手确认消息包长度后调用 `set_max_body_size`，接收缓冲随之调整，超过上限的消息包视为协议错误。
+pub struct FramedStream<T: Transport = Box<dyn Transport>> {
+    stream: T,
+    // 发送消息时使用的协议版本
+    version: u8,
+    // 已从 socket 读出、尚未组成完整消息包的字节
+    pending: Vec<u8>,
+    // 单次读取使用的缓冲区，长度为一个最大消息包
+    read_buf: Vec<u8>,
+    // 允许接收的消息体长度上限
+    max_body: usize,
+    // 单次读取的超时（当前阶段）
+    read_timeout: Option<Duration>,
+    // 整个传输的截止时间
+    deadline: Option<Instant>,
+}
+
+impl<T: Transport> FramedStream<T> {
+    pub fn new(stream: T) -> Self {
+        Self {
+            stream,
+            version: PROTOCOL_VERSION,
+            pending: Vec::with_capacity(MAX_MESSAGE_PACKET_SIZE),
+            read_buf: vec![0u8; MAX_MESSAGE_PACKET_SIZE],
+            max_body: MAX_MESSAGE_BODY_SIZE,
+            read_timeout: None,
+            deadline: None,
+        }
+    }
+
+    /// 指定发送时使用的协议版本（与旧版服务端通信时设置为 v1）
+    pub fn with_version(stream: T, version: u8) -> Self {
+        let mut framed = Self::new(stream);
+        framed.version = version;
+        framed
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// 修改发送时使用的协议版本（服务端按客户端 START 的版本回复）
+    pub fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+
+    /// 按握手确认的消息包长度设置消息体上限，并调整接收缓冲区
+    pub fn set_max_body_size(&mut self, max_body: usize) {
+        self.max_body = max_body;
+        let packet_size = MESSAGE_HEADER_SIZE_V2 + max_body;
+        self.read_buf.resize(packet_size, 0);
+        self.pending.reserve(packet_size);
+    }
+
+    pub fn max_body_size(&self) -> usize {
+        self.max_body
+    }
+
+    /// 设置当前阶段每次读取的超时，None 表示一直阻塞
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.read_timeout = timeout;
+    }
+
+    /// 设置写超时
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.stream.set_write_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// 设置整个传输的截止时间，超过后任何读取都返回超时错误
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    /// 获取底层连接（用于关闭连接等操作）
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.stream
+    }
+
+    /// 读取一个完整的消息包
+    ///
+    /// 按首字节的协议版本确定消息头长度，因此 v1 与 v2 的对端都能正确分帧。
+    pub fn read_packet(&mut self) -> Result<MessagePacket> {
+        // 1. 根据版本号读满消息头
+        self.fill_to(1)?;
+        let header_size = MessageHeader::header_size(self.pending[0])
+            .ok_or(DecodeError::BadVersion(self.pending[0]))?;
+        self.fill_to(header_size)?;
+        let header = MessageHeader::from_bytes(&self.pending[..header_size])?;
+        if header.body_len as usize > self.max_body {
+            return Err(DecodeError::LengthMismatch { expected: self.max_body, actual: header.body_len as usize }.into());
+        }
+
+        // 2. 读满消息体
+        let frame_len = header_size + header.body_len as usize;
+        self.fill_to(frame_len)?;
+
+        // 3. 取出一个完整的消息包，剩余字节留给下一次调用
+        let packet = MessagePacket::try_from_bytes(&self.pending[..frame_len])?;
+        self.pending.drain(..frame_len);
+        Ok(packet)
+    }
+
+    /// 从 socket 读取，直到缓冲区中至少有 `len` 个字节
+    fn fill_to(&mut self, len: usize) -> Result<()> {
+        while self.pending.len() < len {
+            let timeout = self.effective_timeout()?;
+            self.stream.set_read_timeout(timeout)?;
+            let n = match self.stream.read(&mut self.read_buf) {
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) if is_timeout(&e) => {
+                    return Err(ClientError::Timeout(format!("等待对端数据超过 {:?}", timeout.unwrap_or_default())));
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if n == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("连接已关闭: 期望 {} 字节, 仅收到 {} 字节", len, self.pending.len()),
+                ).into());
+            }
+            self.pending.extend_from_slice(&self.read_buf[..n]);
+        }
+        Ok(())
+    }
+
+    /// 本次读取的超时：阶段超时与截止时间剩余时长中较小者
+    fn effective_timeout(&self) -> Result<Option<Duration>> {
+        let remaining = match self.deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(ClientError::Timeout("传输总时长超限".to_string()));
+                }
+                Some(deadline - now)
+            }
+            None => None,
+        };
+        Ok(match (self.read_timeout, remaining) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        })
+    }
+}
+
+/// 阻塞 socket 超时时，不同平台分别返回 WouldBlock 或 TimedOut
+pub fn is_timeout(e: &io::Error) -> bool {
+    matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+}
+
+impl<T: Transport> Write for FramedStream<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
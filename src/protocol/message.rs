@@ -1,22 +1,22 @@
-use crate::protocol::consts::MESSAGE_HEADER_SIZE;
-
 use super::msg_header::MessageHeader;
+use super::msg_type::MessageType;
+use super::error::DecodeError;
 use super::utils;
 use super::consts::*;
 
 /// 通用消息包结构
 #[derive(Clone)]
 pub struct MessagePacket {
-    /// 消息头 (20字节)
+    /// 消息头 (v1: 20字节, v2: 28字节)
     pub header: MessageHeader,
-    /// 消息体 (最大4076字节)
+    /// 消息体 (未协商时最大 1004 字节，握手后不超过协商的消息包长度减去消息头)
     pub body: Vec<u8>,
 }
 
 impl MessagePacket {
 
     // new method
-    pub fn new(msg_type: u8, total_size: u32, chunk_index: u32, chunk_count: u32) -> Self {
+    pub fn new(msg_type: MessageType, total_size: u32, chunk_index: u32, chunk_count: u32) -> Self {
         Self {
             header: MessageHeader::new(msg_type, total_size, chunk_index, chunk_count),
             body: Vec::new(),
@@ -24,13 +24,12 @@ impl MessagePacket {
     }
 
     /// 从Vec<u8>的指定区间生成 MessageBody, 并计算校验和
-    pub fn from_slice(&mut self, src: &[u8], start: usize) {
-        let end = std::cmp::min(start + MAX_MESSAGE_BODY_SIZE, src.len());
+    ///
+    /// 消息体最多 `max_body` 字节（由握手确认的消息包长度决定）。
+    pub fn from_slice(&mut self, src: &[u8], start: usize, max_body: usize) {
+        let end = std::cmp::min(start + max_body, src.len());
         let slice = src[start..end].to_vec();
-        self.body = slice;
-
-        // 计算校验和
-        self.header.checksum = utils::calculate_checksum(&self.body);
+        self.set_body(slice);
 
         // 测试用例，可以删除
         // if start == MAX_MESSAGE_BODY_SIZE + MAX_MESSAGE_BODY_SIZE {
@@ -39,6 +38,26 @@ impl MessagePacket {
     }
 
 
+    /// 设置消息体，同时更新消息体长度和校验和
+    pub fn set_body(&mut self, body: Vec<u8>) {
+        self.body = body;
+        self.header.body_len = self.body.len() as u32;
+
+        // 计算校验和
+        self.header.checksum = utils::checksum_for(self.header.version, &self.body);
+    }
+
+    /// 切换协议版本，并按新版本重新计算校验和
+    pub fn set_version(&mut self, version: u8) {
+        // v1 接收方按分片规则推导消息体长度，只有 DATA 消息可以携带消息体
+        if version == PROTOCOL_VERSION_V1 && self.header.msg_type != MessageType::Data {
+            self.body.clear();
+        }
+        self.header.version = version;
+        self.header.body_len = self.body.len() as u32;
+        self.header.checksum = utils::checksum_for(version, &self.body);
+    }
+
     /// 序列化消息包
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = self.header.to_bytes();
@@ -51,30 +70,41 @@ impl MessagePacket {
     }
 
     /// 反序列化消息包
-    pub fn from_bytes(bytes: &[u8]) -> Self {
-        let header = MessageHeader::from_bytes(bytes).unwrap_or_else(|| {
-            panic!("消息头长度不足，无法从字节数组反序列化消息头");
-        });
-
-        if bytes.len() == MESSAGE_HEADER_SIZE {
-            return MessagePacket {
-                header,
-                body: Vec::new(),
-            };
+    ///
+    /// `bytes` 必须恰好是一个完整的消息包（消息头 + 消息体），任何格式问题都返回 DecodeError。
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let header = MessageHeader::from_bytes(bytes)?;
+
+        let expected = header.encoded_len() + header.body_len as usize;
+        if bytes.len() != expected {
+            return Err(DecodeError::LengthMismatch { expected, actual: bytes.len() });
         }
-        
-        let body = bytes[MESSAGE_HEADER_SIZE..].to_vec();
-        MessagePacket {
+
+        let body = bytes[header.encoded_len()..].to_vec();
+        Ok(MessagePacket {
             header,
             body,
+        })
+    }
+
+    /// 校验消息体与消息头中的校验和是否一致
+    pub fn verify_checksum(&self) -> Result<(), DecodeError> {
+        let actual = utils::checksum_for(self.header.version, &self.body);
+        if actual != self.header.checksum {
+            return Err(DecodeError::Checksum {
+                chunk_index: self.header.chunk_index,
+                expected: self.header.checksum,
+                actual,
+            });
         }
+        Ok(())
     }
 
 
     /// 获取序列化后的总长度
     pub fn get_len(&self) -> usize {
-        // Header 长度 (20) + Body 数据长度
-        20 + self.body.len()
+        // Header 长度 (v1: 20, v2: 28) + Body 数据长度
+        self.header.encoded_len() + self.body.len()
     }
 
 
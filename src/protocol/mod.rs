@@ -2,5 +2,15 @@ pub mod message;
 pub mod msg_header;
 pub mod consts;
 pub mod utils;
+pub mod frame;
+pub mod msg_type;
+pub mod error;
+pub mod control;
+pub mod window;
 
-pub use self::message::MessagePacket;
\ No newline at end of file
+pub use self::message::MessagePacket;
+pub use self::frame::FramedStream;
+pub use self::msg_type::MessageType;
+pub use self::error::DecodeError;
+pub use self::control::{ErrorCode, ErrorReport, StartAck, StartRequest};
+pub use self::window::{Window, WindowAck, WindowReceiver, WindowSender};
\ No newline at end of file
@@ -1,15 +1,23 @@
 
 use std::convert::TryInto;
 use super::consts::*;
+use super::error::DecodeError;
+use super::msg_type::MessageType;
 
 #[derive(Debug, Clone)]
 /// 协议头结构体
+///
+/// v1 线上格式 (20字节):
+/// version | msg_type | message_id | total_size | chunk_index | chunk_count | reserved | checksum(1)
+///
+/// v2 线上格式 (28字节):
+/// version | msg_type | message_id | total_size | chunk_index | chunk_count | body_len | reserved | 填充(1) | checksum(4)
 #[repr(C)]
 pub struct MessageHeader {
     // 协议版本 (1字节)
     pub version: u8,
     // 消息类型 (1字节) (START=0x01, DATA=0x02, END=0x03, ACK=0x04, ERROR=0x05)
-    pub msg_type: u8,
+    pub msg_type: MessageType,
     // 消息标识符 (4字节)，用于区分并发传输
     pub message_id: u32,
     // 数据总大小 (4字节)
@@ -18,14 +26,16 @@ pub struct MessageHeader {
     pub chunk_index: u32,
     // 总分片数 (4字节)
     pub chunk_count: u32,
+    // 本消息包的消息体长度 (4字节，仅 v2；v1 由接收方推导)
+    pub body_len: u32,
     // 保留字段 (1字节)
     pub reserved: u8,
-    // 数据校验和 (1字节，简单累加和) 
-    pub checksum: u8,
+    // 数据校验和 (v1: 1字节简单累加和; v2: 4字节)
+    pub checksum: u32,
 }
 
 impl MessageHeader {
-    pub fn new(msg_type: u8, total_size: u32, chunk_index: u32, chunk_count: u32) -> Self {
+    pub fn new(msg_type: MessageType, total_size: u32, chunk_index: u32, chunk_count: u32) -> Self {
         Self {
             version: PROTOCOL_VERSION,
             msg_type,
@@ -33,40 +43,137 @@ impl MessageHeader {
             total_size,
             chunk_index,
             chunk_count,
+            body_len: 0,
             reserved: 0,   // 保留字段置0
             checksum: 0,   // 后期完善校验算法
         }
     }
 
+    /// 指定协议版本的消息头长度，未知版本返回 None
+    pub fn header_size(version: u8) -> Option<usize> {
+        match version {
+            PROTOCOL_VERSION_V1 => Some(MESSAGE_HEADER_SIZE_V1),
+            PROTOCOL_VERSION_V2 => Some(MESSAGE_HEADER_SIZE_V2),
+            _ => None,
+        }
+    }
+
+    /// 当前消息头序列化后的长度
+    pub fn encoded_len(&self) -> usize {
+        Self::header_size(self.version).unwrap_or(MESSAGE_HEADER_SIZE_V2)
+    }
+
+    /// 按 version 字段选择编码格式
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(MESSAGE_HEADER_SIZE);
+        match self.version {
+            PROTOCOL_VERSION_V1 => self.to_bytes_v1(),
+            _ => self.to_bytes_v2(),
+        }
+    }
+
+    fn to_bytes_v1(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(MESSAGE_HEADER_SIZE_V1);
+        bytes.push(self.version);
+        bytes.push(self.msg_type.into());
+        bytes.extend_from_slice(&self.message_id.to_be_bytes());
+        bytes.extend_from_slice(&self.total_size.to_be_bytes());
+        bytes.extend_from_slice(&self.chunk_index.to_be_bytes());
+        bytes.extend_from_slice(&self.chunk_count.to_be_bytes());
+        bytes.push(self.reserved);
+        bytes.push(self.checksum as u8);
+        bytes
+    }
+
+    fn to_bytes_v2(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(MESSAGE_HEADER_SIZE_V2);
         bytes.push(self.version);
-        bytes.push(self.msg_type);
+        bytes.push(self.msg_type.into());
         bytes.extend_from_slice(&self.message_id.to_be_bytes());
         bytes.extend_from_slice(&self.total_size.to_be_bytes());
         bytes.extend_from_slice(&self.chunk_index.to_be_bytes());
         bytes.extend_from_slice(&self.chunk_count.to_be_bytes());
+        bytes.extend_from_slice(&self.body_len.to_be_bytes());
         bytes.push(self.reserved);
-        bytes.push(self.checksum);
+        bytes.push(0);   // 填充，使校验和 4 字节对齐
+        bytes.extend_from_slice(&self.checksum.to_be_bytes());
         bytes
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
-        if bytes.len() < MESSAGE_HEADER_SIZE {
-            return None; 
+    /// 根据首字节的 version 选择解码格式
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let version = *bytes.first().ok_or(DecodeError::ShortHeader {
+            expected: MESSAGE_HEADER_SIZE_V1,
+            actual: 0,
+        })?;
+        let header_size = Self::header_size(version).ok_or(DecodeError::BadVersion(version))?;
+        if bytes.len() < header_size {
+            return Err(DecodeError::ShortHeader { expected: header_size, actual: bytes.len() });
         }
-        Some(MessageHeader {
+        match version {
+            PROTOCOL_VERSION_V1 => Self::from_bytes_v1(bytes),
+            _ => Self::from_bytes_v2(bytes),
+        }
+    }
+
+    fn from_bytes_v1(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut header = MessageHeader {
             version: bytes[0],
-            msg_type: bytes[1],
-            message_id: u32::from_be_bytes(bytes[2..6].try_into().ok()?),
-            total_size: u32::from_be_bytes(bytes[6..10].try_into().ok()?),
-            chunk_index: u32::from_be_bytes(bytes[10..14].try_into().ok()?),
-            chunk_count: u32::from_be_bytes(bytes[14..18].try_into().ok()?),
+            msg_type: MessageType::try_from(bytes[1])?,
+            message_id: read_u32(bytes, 2),
+            total_size: read_u32(bytes, 6),
+            chunk_index: read_u32(bytes, 10),
+            chunk_count: read_u32(bytes, 14),
+            body_len: 0,
             reserved: bytes[18],
-            checksum: bytes[19],
+            checksum: bytes[19] as u32,
+        };
+        // v1 没有消息体长度字段，由分片规则推导
+        header.body_len = header.infer_body_len_v1()? as u32;
+        Ok(header)
+    }
+
+    fn from_bytes_v2(bytes: &[u8]) -> Result<Self, DecodeError> {
+        // 消息体长度来自对端，超过可协商的上限直接拒绝，避免按异常长度分配内存
+        let body_len = read_u32(bytes, 18);
+        let max_body = MAX_PACKET_SIZE as usize - MESSAGE_HEADER_SIZE_V2;
+        if body_len as usize > max_body {
+            return Err(DecodeError::LengthMismatch {
+                expected: max_body,
+                actual: body_len as usize,
+            });
+        }
+        Ok(MessageHeader {
+            version: bytes[0],
+            msg_type: MessageType::try_from(bytes[1])?,
+            message_id: read_u32(bytes, 2),
+            total_size: read_u32(bytes, 6),
+            chunk_index: read_u32(bytes, 10),
+            chunk_count: read_u32(bytes, 14),
+            body_len,
+            reserved: bytes[22],
+            checksum: read_u32(bytes, 24),
         })
     }
 
+    /// v1 消息体长度推导
+    ///
+    /// 只有 DATA 消息携带消息体：除最后一个分片外，每个分片都是 MAX_MESSAGE_BODY_SIZE 字节，
+    /// 最后一个分片为剩余的字节数。
+    fn infer_body_len_v1(&self) -> Result<usize, DecodeError> {
+        if self.msg_type != MessageType::Data {
+            return Ok(0);
+        }
+        let offset = self.chunk_index as usize * MAX_MESSAGE_BODY_SIZE;
+        let total = self.total_size as usize;
+        if offset >= total {
+            return Err(DecodeError::ChunkOutOfRange {
+                chunk_index: self.chunk_index,
+                total_size: self.total_size,
+            });
+        }
+        Ok(std::cmp::min(total - offset, MAX_MESSAGE_BODY_SIZE))
+    }
+
     pub fn set_message_id(&mut self, msg_id: u32) {
         self.message_id = msg_id;
     }
@@ -75,3 +182,8 @@ impl MessageHeader {
         self.reserved = reserved;
     }
 }
+
+// 调用方已检查长度，这里直接按大端读取
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap_or_default())
+}
@@ -0,0 +1,52 @@
+// src/protocol/msg_type.rs
+use std::fmt;
+use super::consts::*;
+use super::error::DecodeError;
+
+/// 消息类型
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageType {
+    Start = MSG_TYPE_START,
+    Data = MSG_TYPE_DATA,
+    End = MSG_TYPE_END,
+    Ack = MSG_TYPE_ACK,
+    Error = MSG_TYPE_ERROR,
+    AllEnd = MSG_TYPE_ALL_END,
+}
+
+impl TryFrom<u8> for MessageType {
+    type Error = DecodeError;
+
+    fn try_from(value: u8) -> Result<Self, DecodeError> {
+        match value {
+            MSG_TYPE_START => Ok(MessageType::Start),
+            MSG_TYPE_DATA => Ok(MessageType::Data),
+            MSG_TYPE_END => Ok(MessageType::End),
+            MSG_TYPE_ACK => Ok(MessageType::Ack),
+            MSG_TYPE_ERROR => Ok(MessageType::Error),
+            MSG_TYPE_ALL_END => Ok(MessageType::AllEnd),
+            other => Err(DecodeError::UnknownType(other)),
+        }
+    }
+}
+
+impl From<MessageType> for u8 {
+    fn from(msg_type: MessageType) -> u8 {
+        msg_type as u8
+    }
+}
+
+impl fmt::Display for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MessageType::Start => "START",
+            MessageType::Data => "DATA",
+            MessageType::End => "END",
+            MessageType::Ack => "ACK",
+            MessageType::Error => "ERROR",
+            MessageType::AllEnd => "ALL_END",
+        };
+        write!(f, "{}", name)
+    }
+}
@@ -1,16 +1,55 @@
-use vsock::VsockStream;
-use std::io::{Read, Write};
-use anyhow::Result;
+use std::io::Write;
+use crate::error::{ClientError, Result};
+use sha2::{Digest, Sha256};
 use crate::protocol::message::MessagePacket;
+use crate::protocol::frame::FramedStream;
+use crate::transport::Transport;
+use crate::protocol::msg_type::MessageType;
+use crate::protocol::control::{ErrorReport, StartAck, StartRequest};
 use crate::protocol::consts::*;
 
 pub fn calculate_checksum(data: &[u8]) -> u8 {
     data.iter().fold(0u8, |acc, &x| acc.wrapping_add(x))
 }
 
-pub fn send_start_message(stream: &mut VsockStream, msg_id: u32, command: u8) -> Result<()> {
-    let mut startmsg = MessagePacket::new(MSG_TYPE_START, 0, 0, 0);
+/// 按协议版本计算消息体校验和
+///
+/// v1 消息头只有 1 字节校验和，仍使用累加和；v2 使用 CRC32C。
+pub fn checksum_for(version: u8, data: &[u8]) -> u32 {
+    match version {
+        PROTOCOL_VERSION_V1 => calculate_checksum(data) as u32,
+        _ => crc32c::crc32c(data),
+    }
+}
+
+/// 计算完整负载（重组后的压缩数据）的 SHA-256 摘要，随 END 消息发送
+pub fn payload_digest(data: &[u8]) -> [u8; PAYLOAD_DIGEST_SIZE] {
+    Sha256::digest(data).into()
+}
+
+/// 校验 END 携带的负载摘要，`digest` 为接收方计算的摘要
+///
+/// v2 的 END 必须携带摘要，缺失或长度不对都视为校验失败；v1 的 END 没有消息体，不校验。
+pub fn verify_payload_digest(end: &MessagePacket, digest: &[u8]) -> Result<()> {
+    if end.header.version == PROTOCOL_VERSION_V1 {
+        return Ok(());
+    }
+    if end.body.len() != PAYLOAD_DIGEST_SIZE {
+        return Err(ClientError::Checksum(format!(
+            "END 缺少负载摘要: 期望 {} 字节, 收到 {} 字节", PAYLOAD_DIGEST_SIZE, end.body.len()
+        )));
+    }
+    if end.body.as_slice() != digest {
+        return Err(ClientError::Checksum("负载摘要不匹配".to_string()));
+    }
+    Ok(())
+}
+
+pub fn send_start_message<T: Transport>(stream: &mut FramedStream<T>, msg_id: u32, command: u8, request: &StartRequest) -> Result<()> {
+    let mut startmsg = MessagePacket::new(MessageType::Start, request.total_size, 0, request.chunk_count);
     startmsg.header.set_message_id(msg_id);
+    startmsg.set_body(request.body_bytes());
+    startmsg.set_version(stream.version());
     startmsg.header.set_reserved(command);  // 在待定字段设置命令编号
     // println!("发送开始消息，长度是 20 吗？ ? {}", startmsg.get_len() == startmsg.to_bytes().len());
 
@@ -19,63 +58,129 @@ pub fn send_start_message(stream: &mut VsockStream, msg_id: u32, command: u8) ->
     Ok(())
 }
 
-pub fn send_end_message(stream: &mut VsockStream, msg_id: u32) -> Result<()> {
-    let mut endmsg = MessagePacket::new(MSG_TYPE_END, 0, 0, 0);
-    endmsg.header.set_message_id(msg_id);
-    // println!("发送结束消息，长度是 20 吗？ ? {}", endmsg.get_len() == endmsg.to_bytes().len());
+pub fn send_ack_message<T: Transport>(stream: &mut FramedStream<T>, msg_id: u32) -> Result<()> {
+    send_ack_for_chunk(stream, msg_id, 0)
+}
 
-    stream.write_all(&endmsg.to_bytes())?;
+/// 发送携带 chunk_index 的 ACK，chunk_index 表示已确认的分片数
+pub fn send_ack_for_chunk<T: Transport>(stream: &mut FramedStream<T>, msg_id: u32, chunk_index: u32) -> Result<()> {
+    let mut ackmsg = MessagePacket::new(MessageType::Ack, 0, chunk_index, 0);
+    ackmsg.header.set_message_id(msg_id);
+    ackmsg.set_version(stream.version());
+    // println!("发送ACK消息，长度是 20 吗？ ? {}", ackmsg.get_len() == ackmsg.to_bytes().len());
+
+    stream.write_all(&ackmsg.to_bytes())?;
     stream.flush()?;
     Ok(())
 }
 
-#[allow(dead_code)]
-pub fn send_ack_message(stream: &mut VsockStream, msg_id: u32) -> Result<()> {
-    let mut ackmsg = MessagePacket::new(MSG_TYPE_ACK, 0, 0, 0);
+
+/// 回复 START：chunk_index 为已确认的分片数，消息体为接受的编码、窗口和消息包长度（仅 v2）
+///
+/// 之后按确认的消息包长度接收。
+pub fn send_start_ack<T: Transport>(stream: &mut FramedStream<T>, msg_id: u32, ack: &StartAck) -> Result<()> {
+    let mut ackmsg = MessagePacket::new(MessageType::Ack, 0, ack.resume_from, 0);
     ackmsg.header.set_message_id(msg_id);
-    // println!("发送ACK消息，长度是 20 吗？ ? {}", ackmsg.get_len() == ackmsg.to_bytes().len());
+    ackmsg.set_body(ack.body_bytes());
+    ackmsg.set_version(stream.version());
 
     stream.write_all(&ackmsg.to_bytes())?;
     stream.flush()?;
+    apply_max_body_size(stream, ack);
     Ok(())
 }
 
+/// 按 START 确认的消息包长度调整接收上限
+///
+/// 多路复用时同一连接上的传输可能确认了不同的长度，接收上限只增大不缩小。
+fn apply_max_body_size<T: Transport>(stream: &mut FramedStream<T>, ack: &StartAck) {
+    stream.set_max_body_size(stream.max_body_size().max(ack.max_body_size()));
+}
+
+/// 发送 ERROR：chunk_index 为错误码，消息体为 UTF-8 原因（仅 v2，超长时截断）
+pub fn send_error_message<T: Transport>(stream: &mut FramedStream<T>, msg_id: u32, report: &ErrorReport) -> Result<()> {
+    let mut end = report.reason.len().min(MAX_ERROR_REASON_SIZE);
+    while !report.reason.is_char_boundary(end) {
+        end -= 1;
+    }
+    let mut errmsg = MessagePacket::new(MessageType::Error, 0, report.code.into(), 0);
+    errmsg.header.set_message_id(msg_id);
+    errmsg.set_body(report.reason.as_bytes()[..end].to_vec());
+    errmsg.set_version(stream.version());
+
+    stream.write_all(&errmsg.to_bytes())?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// 收到 ERROR 时转换为 `ClientError::Remote`，其他消息原样返回
+pub fn check_error(packet: MessagePacket) -> Result<MessagePacket> {
+    if packet.header.msg_type == MessageType::Error {
+        return Err(ClientError::Remote(ErrorReport::from_packet(packet.header.chunk_index, &packet.body)));
+    }
+    Ok(packet)
+}
 
-pub fn send_data_message(stream: &mut VsockStream, datamsg: &MessagePacket) -> Result<()> {    
+pub fn send_data_message<T: Transport>(stream: &mut FramedStream<T>, datamsg: &MessagePacket) -> Result<()> {    
     // 写入消息头和数据
-    let buf = datamsg.to_bytes();
+    let buf = if datamsg.header.version == stream.version() {
+        datamsg.to_bytes()
+    } else {
+        let mut packet = datamsg.clone();
+        packet.set_version(stream.version());
+        packet.to_bytes()
+    };
     stream.write_all(&buf)?;
     Ok(())
 }
 
-#[allow(dead_code)]
-pub fn receive_data_message(stream: &mut VsockStream, buf: &mut [u8]) -> Result<MessagePacket> {
-    let n = stream.read(buf)?;
-    let packet = MessagePacket::from_bytes(&buf[..n]);
-    Ok(packet)
+/// 读取一个完整的消息包（由 FramedStream 负责分帧）
+pub fn receive_data_message<T: Transport>(stream: &mut FramedStream<T>) -> Result<MessagePacket> {
+    stream.read_packet()
 }
 
-pub fn wait_for_ack(stream: &mut VsockStream, expected_msg_id: u32) -> bool {
-    let mut packet_buf = [0u8; MESSAGE_HEADER_SIZE];
-
-    match stream.read_exact(&mut packet_buf) {
-        Ok(_) => {
-            let packet = MessagePacket::from_bytes(&packet_buf);
-            if packet.header.msg_type == MSG_TYPE_ACK && packet.header.message_id == expected_msg_id {
-                true
-            } else {
-                eprintln!("收到非预期ACK: type={}, id={}", packet.header.msg_type, packet.header.message_id);
-                false
-            }
-        }
-        Err(e) => {
-            eprintln!("等待ACK错误: {:?}", e);
-            false
-        }
+/// 等待对端的 ACK
+///
+/// 收到 ERROR 时返回 `ClientError::Remote`，收到其他消息或其他 message_id 的 ACK 视为拒绝。
+pub fn wait_for_ack<T: Transport>(stream: &mut FramedStream<T>, expected_msg_id: u32) -> Result<MessagePacket> {
+    let packet = check_error(stream.read_packet()?)?;
+    if packet.header.msg_type == MessageType::Ack && packet.header.message_id == expected_msg_id {
+        Ok(packet)
+    } else {
+        Err(ClientError::Nack(format!(
+            "收到非预期ACK: type={}, id={}", packet.header.msg_type, packet.header.message_id
+        )))
+    }
+}
+
+/// 握手：发送 START 消息并等待服务端确认
+///
+/// 返回服务端 ACK 中的续传起点、接受的编码、窗口和消息包长度，之后按确认的消息包长度接收。
+pub fn start_handshake<T: Transport>(stream: &mut FramedStream<T>, msg_id: u32, command: u8, request: &StartRequest) -> Result<StartAck> {
+    send_start_message(stream, msg_id, command, request)?;
+    let ack = wait_for_ack(stream, msg_id).map_err(|e| match e {
+        ClientError::Nack(reason) => ClientError::Handshake(format!("Server not ready: {}", reason)),
+        other => other,
+    })?;
+    accept_start_ack(stream, request, &ack)
+}
+
+/// 解析服务端对 START 的 ACK，并按确认的消息包长度调整接收上限
+pub fn accept_start_ack<T: Transport>(stream: &mut FramedStream<T>, request: &StartRequest, packet: &MessagePacket) -> Result<StartAck> {
+    let ack = StartAck::from_packet(packet.header.chunk_index, &packet.body);
+
+    // 服务端只能确认不超过客户端提出的消息包长度
+    let size = ack.max_packet_size;
+    if size != 0 && !(MAX_MESSAGE_PACKET_SIZE as u32..=request.max_packet_size).contains(&size) {
+        return Err(ClientError::Handshake(format!(
+            "服务端确认的消息包长度 {} 超出范围 [{}, {}]", size, MAX_MESSAGE_PACKET_SIZE, request.max_packet_size
+        )));
     }
+    apply_max_body_size(stream, &ack);
+    Ok(ack)
 }
 
-// pub fn wait_final_response(stream: &mut VsockStream, expected_msg_id: u32) -> Result<String> {
+// pub fn wait_final_response(stream: &mut FramedStream, expected_msg_id: u32) -> Result<String> {
 //     let mut buffer = Vec::new();
 //     let mut header_buf = [0u8; HEADER_SIZE];
     
@@ -0,0 +1,254 @@
+// src/protocol/window.rs
+// 滑动窗口：发送方最多保留 `size` 个未确认的分片，接收方按累计 + 选择确认回复 ACK
+//
+// ACK 消息头的 chunk_index 为累计确认数（按顺序收到的分片数），消息体为已收到的
+// 乱序分片编号（每个 4 字节，仅 v2）。分片校验失败时接收方立即回复 ACK，
+// reserved 置 ACK_FLAG_RETRANSMIT，chunk_count 为需要重传的分片编号。
+//
+// save 与 dump 使用同一套发送 / 接收逻辑，只是方向相反。
+use std::collections::{BTreeMap, VecDeque};
+use std::io::Write;
+use crate::error::{ClientError, Result};
+use crate::transport::Transport;
+use super::consts::*;
+use super::frame::FramedStream;
+use super::message::MessagePacket;
+use super::msg_type::MessageType;
+use super::utils;
+
+/// 协商后的窗口参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    /// 未确认分片数上限
+    pub size: u32,
+    /// 接收方每按顺序收到多少个分片回复一次 ACK
+    pub ack_every: u32,
+    /// 对端是否支持累计 / 选择确认；旧版对端的 ACK 不携带确认位置
+    pub selective: bool,
+}
+
+impl Window {
+    /// 由握手确认的窗口大小生成参数
+    ///
+    /// 0 表示对端不支持滑动窗口，退回旧版方式：发送方每发送 `legacy_ack_every` 个分片等待一次 ACK。
+    pub fn negotiated(size: u32, legacy_ack_every: u32) -> Self {
+        if size == 0 {
+            return Self { size: legacy_ack_every, ack_every: legacy_ack_every, selective: false };
+        }
+        let size = size.min(MAX_WINDOW_SIZE);
+        // 确认间隔不超过窗口的一半，发送方等待 ACK 时管道中仍有数据
+        Self { size, ack_every: (size / 2).max(1), selective: true }
+    }
+}
+
+/// 接收方回复的 ACK
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WindowAck {
+    /// 按顺序收到的分片数
+    pub cumulative: u32,
+    /// 已收到但尚未按顺序交付的分片
+    pub selective: Vec<u32>,
+    /// 校验失败、需要重传的分片
+    pub retransmit: Option<u32>,
+}
+
+impl WindowAck {
+    pub fn to_packet(&self, msg_id: u32, version: u8) -> MessagePacket {
+        let mut packet = MessagePacket::new(MessageType::Ack, 0, self.cumulative, self.retransmit.unwrap_or(0));
+        packet.header.set_message_id(msg_id);
+        if self.retransmit.is_some() {
+            packet.header.set_reserved(ACK_FLAG_RETRANSMIT);
+        }
+        packet.set_body(self.selective.iter().flat_map(|i| i.to_be_bytes()).collect());
+        packet.set_version(version);
+        packet
+    }
+
+    pub fn from_packet(packet: &MessagePacket) -> Self {
+        let selective = packet
+            .body
+            .chunks_exact(4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        let retransmit = (packet.header.reserved & ACK_FLAG_RETRANSMIT != 0).then_some(packet.header.chunk_count);
+        Self { cumulative: packet.header.chunk_index, selective, retransmit }
+    }
+}
+
+/// 发送方
+pub struct WindowSender {
+    window: Window,
+    msg_id: u32,
+    // 最早的未确认分片编号
+    base: u32,
+    // base 开始的已发送分片，被选择确认的置为 None
+    in_flight: VecDeque<Option<MessagePacket>>,
+    retransmits: u32,
+}
+
+impl WindowSender {
+    /// `start` 为第一个要发送的分片编号（续传时为服务端已确认的分片数）
+    pub fn new(window: Window, msg_id: u32, start: u32) -> Self {
+        Self { window, msg_id, base: start, in_flight: VecDeque::new(), retransmits: 0 }
+    }
+
+    /// 窗口未满，可以立即发送下一个分片
+    pub fn can_send(&self) -> bool {
+        (self.in_flight.len() as u32) < self.window.size
+    }
+
+    /// 已发送、尚未确认的分片数
+    pub fn in_flight(&self) -> u32 {
+        self.in_flight.len() as u32
+    }
+
+    /// 发送一个 DATA 分片，窗口已满时先等待 ACK
+    pub fn send<T: Transport>(&mut self, stream: &mut FramedStream<T>, packet: MessagePacket) -> Result<()> {
+        while self.in_flight.len() as u32 >= self.window.size {
+            self.wait_ack(stream)?;
+        }
+        utils::send_data_message(stream, &packet)?;
+        self.in_flight.push_back(Some(packet));
+        Ok(())
+    }
+
+    /// 等待已发送的分片全部确认
+    ///
+    /// 旧版对端不确认最后不足一组的分片，由之后的 END 确认。
+    pub fn finish<T: Transport>(&mut self, stream: &mut FramedStream<T>) -> Result<()> {
+        stream.flush()?;
+        let pending = if self.window.selective { 1 } else { self.window.ack_every };
+        while self.in_flight.len() as u32 >= pending {
+            self.wait_ack(stream)?;
+        }
+        self.base += self.in_flight.len() as u32;
+        self.in_flight.clear();
+        Ok(())
+    }
+
+    fn wait_ack<T: Transport>(&mut self, stream: &mut FramedStream<T>) -> Result<()> {
+        stream.flush()?;
+        let packet = utils::wait_for_ack(stream, self.msg_id)?;
+        self.handle_ack(stream, &packet)
+    }
+
+    /// 处理一个本传输的 ACK：移出已确认的分片，需要时重传
+    ///
+    /// 多路复用时由调用方按 message_id 分发 ACK 后调用。
+    pub fn handle_ack<T: Transport>(&mut self, stream: &mut FramedStream<T>, packet: &MessagePacket) -> Result<()> {
+        if !self.window.selective {
+            let n = self.window.ack_every.min(self.in_flight.len() as u32);
+            self.in_flight.drain(..n as usize);
+            self.base += n;
+            return Ok(());
+        }
+
+        let ack = WindowAck::from_packet(packet);
+        let sent_end = self.base + self.in_flight.len() as u32;
+        if ack.cumulative < self.base || ack.cumulative > sent_end {
+            return Err(ClientError::ProtocolViolation(format!(
+                "确认位置 {} 超出已发送范围 [{}, {}]", ack.cumulative, self.base, sent_end
+            )));
+        }
+        self.in_flight.drain(..(ack.cumulative - self.base) as usize);
+        self.base = ack.cumulative;
+
+        // 已被选择确认的分片不再需要保留
+        for index in &ack.selective {
+            if let Some(slot) = index.checked_sub(self.base).and_then(|i| self.in_flight.get_mut(i as usize)) {
+                *slot = None;
+            }
+        }
+
+        if let Some(index) = ack.retransmit {
+            self.retransmits += 1;
+            if self.retransmits > MAX_RETRANSMITS {
+                return Err(ClientError::Checksum(format!("重传次数超过 {}", MAX_RETRANSMITS)));
+            }
+            let slot = index.checked_sub(self.base).and_then(|i| self.in_flight.get(i as usize));
+            if let Some(Some(packet)) = slot {
+                eprintln!("[Window] ✗ 分片 {} 校验失败，重传", index);
+                utils::send_data_message(stream, packet)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 接收方
+pub struct WindowReceiver {
+    window: Window,
+    msg_id: u32,
+    // 下一个按顺序交付的分片编号
+    next: u32,
+    last_acked: u32,
+    // DATA 消息头中的分片总数，流式发送时只有最后一个分片携带
+    expected: Option<u32>,
+    pending: BTreeMap<u32, Vec<u8>>,
+}
+
+impl WindowReceiver {
+    /// `start` 为期望的第一个分片编号（续传时为已收到的分片数）
+    pub fn new(window: Window, msg_id: u32, start: u32) -> Self {
+        Self { window, msg_id, next: start, last_acked: start, expected: None, pending: BTreeMap::new() }
+    }
+
+    /// 已按顺序收到的分片数
+    pub fn received(&self) -> u32 {
+        self.next
+    }
+
+    /// 处理一个 DATA 分片，返回可以按顺序交付的消息体
+    ///
+    /// 满足以下任一条件时回复 ACK：按顺序收到的分片达到确认间隔、全部分片已收到、分片校验失败。
+    /// 重复的分片直接忽略。
+    pub fn receive<T: Transport>(&mut self, stream: &mut FramedStream<T>, packet: MessagePacket) -> Result<Vec<Vec<u8>>> {
+        let index = packet.header.chunk_index;
+        if let Err(e) = packet.verify_checksum() {
+            if !self.window.selective {
+                return Err(e.into());
+            }
+            eprintln!("[Window] ✗ 分片 {} 校验失败，请求重传", index);
+            self.send_ack(stream, Some(index))?;
+            return Ok(Vec::new());
+        }
+
+        if index < self.next || self.pending.contains_key(&index) {
+            return Ok(Vec::new());
+        }
+        if index >= self.next + self.window.size {
+            return Err(ClientError::ProtocolViolation(format!(
+                "分片 {} 超出接收窗口 [{}, {})", index, self.next, self.next + self.window.size
+            )));
+        }
+        if packet.header.chunk_count > 0 {
+            self.expected = Some(packet.header.chunk_count);
+        }
+
+        self.pending.insert(index, packet.body);
+        let mut delivered = Vec::new();
+        while let Some(body) = self.pending.remove(&self.next) {
+            delivered.push(body);
+            self.next += 1;
+        }
+
+        // 旧版发送方不等待最后不足一组的分片的 ACK
+        let complete = self.window.selective && self.expected == Some(self.next) && self.next > self.last_acked;
+        if self.next - self.last_acked >= self.window.ack_every || complete {
+            self.send_ack(stream, None)?;
+        }
+        Ok(delivered)
+    }
+
+    fn send_ack<T: Transport>(&mut self, stream: &mut FramedStream<T>, retransmit: Option<u32>) -> Result<()> {
+        let ack = WindowAck {
+            cumulative: self.next,
+            selective: self.pending.keys().copied().collect(),
+            retransmit,
+        };
+        stream.write_all(&ack.to_packet(self.msg_id, stream.version()).to_bytes())?;
+        stream.flush()?;
+        self.last_acked = self.next;
+        Ok(())
+    }
+}
@@ -0,0 +1,435 @@
+// src/server/mod.rs
+// 黑匣子参考服务端：与 client_thread_save / client_thread_dump 使用相同的对话流程
+pub mod storage;
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+
+use crate::codec::CodecId;
+use crate::data_process;
+use crate::error::ClientError;
+use crate::protocol::{ErrorCode, ErrorReport, FramedStream, MessagePacket, MessageType, StartAck, StartRequest, Window, WindowReceiver, WindowSender};
+use crate::protocol::consts::{LEGACY_DUMP_ACK_INTERVAL, MAX_MESSAGE_PACKET_SIZE, MAX_PACKET_SIZE, MAX_WINDOW_SIZE};
+use crate::protocol::utils as protocol_utils;
+use crate::transport::{Endpoint, Listener, Transport};
+use crate::utils;
+pub use self::storage::{Command, ReportKind, Storage};
+
+// 连接空闲超过该时长即断开，避免异常客户端占用线程
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+// 未完成的传输保留时长，超过后不再允许续传
+const PARTIAL_TRANSFER_TTL: Duration = Duration::from_secs(600);
+// dump 时转换编码使用的压缩级别
+const TRANSCODE_LEVEL: u32 = 6;
+// 单份报告（压缩后）默认的大小上限
+pub const DEFAULT_MAX_REPORT_SIZE: usize = 256 * 1024 * 1024;
+// 接收缓冲区预分配的上限
+const INITIAL_BUFFER_CAPACITY: usize = 1024 * 1024;
+
+/// 未完成的保存传输，客户端重连后可以从已确认的分片继续
+struct PartialTransfer {
+    codec: CodecId,
+    max_packet_size: u32,
+    total_size: u32,
+    chunk_count: u32,
+    // 最近一个携带总数的 DATA 中的 (total_size, chunk_count)，旧版客户端的 END 不携带总数时以此为准
+    data_totals: (u32, u32),
+    data: Vec<u8>,
+    received_chunks: u32,
+    updated_at: Instant,
+}
+
+/// 服务端
+pub struct Server {
+    listener: Listener,
+    endpoint: Endpoint,
+    storage: Arc<Storage>,
+    partials: Arc<Mutex<HashMap<u64, PartialTransfer>>>,
+    idle_timeout: Option<Duration>,
+    max_report_size: usize,
+}
+
+impl Server {
+    pub fn bind(endpoint: Endpoint, storage: Storage) -> Result<Self> {
+        let listener = endpoint.bind()
+            .map_err(|e| anyhow::anyhow!("监听 {} 失败: {}", endpoint, e))?;
+        Ok(Self {
+            listener,
+            endpoint,
+            storage: Arc::new(storage),
+            partials: Arc::new(Mutex::new(HashMap::new())),
+            idle_timeout: Some(DEFAULT_IDLE_TIMEOUT),
+            max_report_size: DEFAULT_MAX_REPORT_SIZE,
+        })
+    }
+
+    /// 设置连接空闲超时，None 表示不限时
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+    }
+
+    /// 设置单份报告（压缩后）的大小上限，超过时回复 ERROR 并丢弃该报告
+    pub fn set_max_report_size(&mut self, size: usize) {
+        self.max_report_size = size;
+    }
+
+    /// 循环接受连接，每个连接一个线程
+    pub fn run(&self) -> Result<()> {
+        println!("[Server] 正在监听 {}，存储目录 {}", self.endpoint, self.storage.root().display());
+        loop {
+            let (stream, peer) = match self.listener.accept() {
+                Ok(conn) => conn,
+                Err(e) => {
+                    eprintln!("[Server] ✗ 接受连接失败: {:?}", e);
+                    continue;
+                }
+            };
+            println!("[Server] ✓ 新连接: {}", peer);
+
+            let session = Session {
+                storage: Arc::clone(&self.storage),
+                partials: Arc::clone(&self.partials),
+                max_report_size: self.max_report_size,
+            };
+            let idle_timeout = self.idle_timeout;
+            thread::spawn(move || {
+                let mut stream = FramedStream::new(stream);
+                stream.set_read_timeout(idle_timeout);
+                if let Err(e) = session.handle(&mut stream) {
+                    eprintln!("[Server] ✗ 连接 {} 处理失败: {}", peer, e);
+                }
+                utils::graceful_shutdown(stream.get_mut(), "[Server]");
+            });
+        }
+    }
+}
+
+/// 单个连接的处理逻辑
+struct Session {
+    storage: Arc<Storage>,
+    partials: Arc<Mutex<HashMap<u64, PartialTransfer>>>,
+    max_report_size: usize,
+}
+
+/// 连接上进行中的保存，按 message_id 区分
+struct ActiveSave {
+    kind: ReportKind,
+    transfer_id: u64,
+    transfer: PartialTransfer,
+    receiver: WindowReceiver,
+}
+
+impl Session {
+    /// 处理一个连接上的全部消息，直到客户端关闭连接
+    ///
+    /// 一个连接上可以交错进行多份报告的保存，DATA / END 按 message_id 交给对应的传输；
+    /// 某一份报告失败时向该 message_id 回复 ERROR，不影响其他传输。
+    fn handle<T: Transport>(&self, stream: &mut FramedStream<T>) -> Result<()> {
+        let mut saves: HashMap<u32, ActiveSave> = HashMap::new();
+        let result = self.serve(stream, &mut saves);
+        // 连接中断：保留已收到的分片，等待客户端重连续传
+        for (_, save) in saves.drain() {
+            self.keep_partial(save);
+        }
+        result
+    }
+
+    fn serve<T: Transport>(&self, stream: &mut FramedStream<T>, saves: &mut HashMap<u32, ActiveSave>) -> Result<()> {
+        // 已回复 ERROR 的传输，客户端收到 ERROR 前已发出的分片直接丢弃
+        let mut aborted: HashSet<u32> = HashSet::new();
+        loop {
+            let packet = match stream.read_packet() {
+                Ok(packet) => packet,
+                // 没有进行中的传输时客户端关闭连接或连接空闲，正常结束
+                Err(ClientError::Io(e)) if saves.is_empty() && e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(ClientError::Timeout(_)) if saves.is_empty() => return Ok(()),
+                Err(e) => return Err(e.into()),
+            };
+
+            let msg_id = packet.header.message_id;
+            if packet.header.msg_type == MessageType::Start {
+                aborted.remove(&msg_id);
+            } else if aborted.contains(&msg_id) {
+                continue;
+            }
+            if let Err(e) = self.dispatch(stream, packet, saves) {
+                // 连接已不可用（I/O 错误、超时）时结束，否则只中止这一份传输
+                let Some(report) = error_report(&e) else { return Err(e) };
+                eprintln!("[Server] ✗ message_id={:#x} 处理失败: {}", msg_id, e);
+                protocol_utils::send_error_message(stream, msg_id, &report)?;
+                aborted.insert(msg_id);
+                // 超过大小上限的报告续传也无法完成，不保留
+                if let Some(save) = saves.remove(&msg_id)
+                    && report.code != ErrorCode::TooLarge {
+                    self.keep_partial(save);
+                }
+            }
+        }
+    }
+
+    fn dispatch<T: Transport>(&self, stream: &mut FramedStream<T>, packet: MessagePacket, saves: &mut HashMap<u32, ActiveSave>) -> Result<()> {
+        let msg_id = packet.header.message_id;
+        match packet.header.msg_type {
+            MessageType::Start => self.handle_start(stream, &packet, saves),
+            MessageType::Data | MessageType::End => {
+                let save = saves.get_mut(&msg_id).ok_or_else(|| {
+                    ErrorReport::new(ErrorCode::Protocol, format!("没有 message_id={:#x} 的传输", msg_id))
+                })?;
+                if self.receive_packet(stream, save, packet)?
+                    && let Some(save) = saves.remove(&msg_id) {
+                    self.finish_save(stream, msg_id, save)?;
+                }
+                Ok(())
+            }
+            // 客户端中止了这一份传输，不再回复 ERROR
+            MessageType::Error => {
+                let report = ErrorReport::from_packet(packet.header.chunk_index, &packet.body);
+                eprintln!("[Server] ✗ 客户端报告错误 message_id={:#x}: {}", msg_id, report);
+                if let Some(save) = saves.remove(&msg_id) {
+                    self.keep_partial(save);
+                }
+                Ok(())
+            }
+            other => Err(ErrorReport::new(ErrorCode::Protocol, format!("收到非预期消息: {}", other)).into()),
+        }
+    }
+
+    /// 处理 START：保存加入进行中的传输；dump 由服务端主导，只能在没有进行中的保存时进行
+    fn handle_start<T: Transport>(&self, stream: &mut FramedStream<T>, start: &MessagePacket, saves: &mut HashMap<u32, ActiveSave>) -> Result<()> {
+        // 按客户端使用的协议版本回复
+        stream.set_version(start.header.version);
+
+        let msg_id = start.header.message_id;
+        let command = Command::from_code(start.header.reserved)
+            .ok_or_else(|| ErrorReport::new(ErrorCode::UnknownCommand, format!("未知命令: {:#04x}", start.header.reserved)))?;
+        println!("[Server] 收到 START: id={:#x}, command={:?}, version={}", msg_id, command, start.header.version);
+
+        if saves.contains_key(&msg_id) {
+            return Err(ErrorReport::new(ErrorCode::Protocol, format!("message_id={:#x} 的传输尚未结束", msg_id)).into());
+        }
+        match command {
+            Command::Save(kind) => {
+                let save = self.start_save(stream, start, kind)?;
+                saves.insert(msg_id, save);
+                Ok(())
+            }
+            Command::Dump(_) if !saves.is_empty() => {
+                Err(ErrorReport::new(ErrorCode::Protocol, "dump 不能与保存交错进行").into())
+            }
+            Command::Dump(kind) => self.handle_dump(stream, start, kind),
+        }
+    }
+
+    /// 开始接收一份报告：START → ACK，之后 (DATA → ACK)* → END → ACK 由 `receive_packet` 处理
+    fn start_save<T: Transport>(&self, stream: &mut FramedStream<T>, start: &MessagePacket, kind: ReportKind) -> Result<ActiveSave> {
+        let msg_id = start.header.message_id;
+        let request = StartRequest::from_packet(start.header.total_size, start.header.chunk_count, &start.body);
+        let transfer = self.take_partial(&request)?;
+
+        // ACK 中的 chunk_index 告诉客户端从哪个分片开始发送，消息体确认编码（支持全部编码，直接接受）
+        if transfer.received_chunks > 0 {
+            println!("[Server] transfer_id={:#x} 从第 {} 个分片续传", request.transfer_id, transfer.received_chunks);
+        }
+        let ack = StartAck {
+            resume_from: transfer.received_chunks,
+            codec: transfer.codec,
+            window: request.window.min(MAX_WINDOW_SIZE),
+            max_packet_size: transfer.max_packet_size,
+        };
+        protocol_utils::send_start_ack(stream, msg_id, &ack)?;
+
+        let receiver = WindowReceiver::new(Window::negotiated(ack.window, 1), msg_id, transfer.received_chunks);
+        Ok(ActiveSave { kind, transfer_id: request.transfer_id, transfer, receiver })
+    }
+
+    /// 保存收齐的报告并回复最后的 ACK
+    fn finish_save<T: Transport>(&self, stream: &mut FramedStream<T>, msg_id: u32, save: ActiveSave) -> Result<()> {
+        let transfer = save.transfer;
+        let path = self.storage.save(save.kind, transfer.codec, &transfer.data)?;
+        protocol_utils::send_ack_for_chunk(stream, msg_id, transfer.received_chunks)?;
+        println!("[Server] ✓ 已保存 {} 字节 ({}) 到 {}", transfer.data.len(), transfer.codec, path.display());
+        Ok(())
+    }
+
+    /// 保留已收到的分片，等待客户端重连续传；transfer_id 为 0 时不保留
+    fn keep_partial(&self, save: ActiveSave) {
+        if save.transfer_id != 0 {
+            let mut transfer = save.transfer;
+            transfer.updated_at = Instant::now();
+            self.partials.lock().unwrap_or_else(|e| e.into_inner()).insert(save.transfer_id, transfer);
+        }
+    }
+
+    /// 取出可续传的传输；transfer_id 为 0、大小、编码或消息包长度不一致时重新开始
+    ///
+    /// 客户端声明的总大小超过上限时拒绝，不按声明的大小预留内存。
+    fn take_partial(&self, request: &StartRequest) -> Result<PartialTransfer> {
+        if request.total_size as usize > self.max_report_size {
+            return Err(self.too_large(request.total_size as usize).into());
+        }
+        let mut partials = self.partials.lock().unwrap_or_else(|e| e.into_inner());
+        partials.retain(|_, p| p.updated_at.elapsed() < PARTIAL_TRANSFER_TTL);
+
+        Ok(match partials.remove(&request.transfer_id) {
+            Some(p) if request.transfer_id != 0
+                && p.total_size == request.total_size
+                && p.chunk_count == request.chunk_count
+                && p.codec == request.codec
+                && p.max_packet_size == negotiate_packet_size(request.max_packet_size) => p,
+            _ => PartialTransfer {
+                codec: request.codec,
+                max_packet_size: negotiate_packet_size(request.max_packet_size),
+                total_size: request.total_size,
+                chunk_count: request.chunk_count,
+                data_totals: (0, 0),
+                // 总大小由对端声明，只预分配有限的容量，之后随数据到达增长
+                data: Vec::with_capacity((request.total_size as usize).min(INITIAL_BUFFER_CAPACITY)),
+                received_chunks: 0,
+                updated_at: Instant::now(),
+            },
+        })
+    }
+
+    fn too_large(&self, size: usize) -> ErrorReport {
+        ErrorReport::new(ErrorCode::TooLarge, format!("报告 {} 字节超过上限 {} 字节", size, self.max_report_size))
+    }
+
+    /// 按窗口接收一个 DATA / END，返回是否收到了完整的报告
+    fn receive_packet<T: Transport>(&self, stream: &mut FramedStream<T>, save: &mut ActiveSave, packet: MessagePacket) -> Result<bool> {
+        let transfer = &mut save.transfer;
+        match packet.header.msg_type {
+            MessageType::Data => {
+                if packet.header.reserved != u8::from(transfer.codec) {
+                    return Err(ErrorReport::new(
+                        ErrorCode::Protocol,
+                        format!("分片编码 {} 与握手确认的编码 {} 不一致", packet.header.reserved, transfer.codec),
+                    ).into());
+                }
+                if packet.header.chunk_count != 0 {
+                    transfer.data_totals = (packet.header.total_size, packet.header.chunk_count);
+                }
+                for body in save.receiver.receive(stream, packet)? {
+                    // 流式发送时 START 中总大小为 0，只能在接收过程中检查上限
+                    let size = transfer.data.len() + body.len();
+                    if size > self.max_report_size {
+                        return Err(self.too_large(size).into());
+                    }
+                    transfer.data.extend_from_slice(&body);
+                }
+                transfer.received_chunks = save.receiver.received();
+                Ok(false)
+            }
+            _ => {
+                // 流式发送时 START / DATA 中总大小为 0，以 END 携带的值为准；
+                // 旧版客户端的 END 为 0/0，以 DATA 中的值为准
+                let (expected_size, expected_chunks) = match (packet.header.total_size, packet.header.chunk_count) {
+                    (0, 0) => transfer.data_totals,
+                    totals => totals,
+                };
+                if transfer.data.len() != expected_size as usize || transfer.received_chunks != expected_chunks {
+                    return Err(ErrorReport::new(ErrorCode::Protocol, format!(
+                        "数据不完整: 期望 {} 字节 / {} 个分片, 收到 {} 字节 / {} 个分片",
+                        expected_size, expected_chunks, transfer.data.len(), transfer.received_chunks
+                    )).into());
+                }
+                // v2 客户端在 END 中携带负载摘要
+                protocol_utils::verify_payload_digest(&packet, Sha256::digest(&transfer.data).as_slice())?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// 发送全部报告：START → ACK → ACK(客户端) → [DATA* → END → ACK ↔ ACK]* → ALL_END → ACK ↔ ACK
+    ///
+    /// 全部报告以客户端在 START 中提出的编码发送，保存时使用其他编码的报告先转换。
+    /// DATA 按协商的消息包长度分片、按协商的窗口发送；旧版客户端每收到 5 个分片回复一次 ACK。
+    fn handle_dump<T: Transport>(&self, stream: &mut FramedStream<T>, start: &MessagePacket, kind: ReportKind) -> Result<()> {
+        let msg_id = start.header.message_id;
+        let request = StartRequest::from_packet(start.header.total_size, start.header.chunk_count, &start.body);
+        let codec = request.codec;
+        let ack = StartAck {
+            resume_from: 0,
+            codec,
+            window: request.window.min(MAX_WINDOW_SIZE),
+            max_packet_size: negotiate_packet_size(request.max_packet_size),
+        };
+        protocol_utils::send_start_ack(stream, msg_id, &ack)?;
+        protocol_utils::wait_for_ack(stream, msg_id)?;
+        let window = Window::negotiated(ack.window, LEGACY_DUMP_ACK_INTERVAL);
+
+        let reports = self.storage.load_all(kind)?;
+        println!("[Server] 共有 {} 份报告需要发送 ({})", reports.len(), codec);
+
+        for (stored_codec, payload) in reports {
+            let payload = if stored_codec == codec {
+                payload
+            } else {
+                let raw = stored_codec.codec().decompress(&payload)?;
+                codec.codec().compress(&raw, TRANSCODE_LEVEL)?
+            };
+            let mut packets = data_process::wrap_message_packets_with(payload, ack.max_body_size());
+            for packet in &mut packets {
+                packet.header.set_message_id(msg_id);
+                packet.header.set_reserved(codec.into());
+            }
+
+            // 最后一个消息包为 END，DATA 全部确认后再发送
+            let end = packets.pop().ok_or_else(|| anyhow::anyhow!("报告没有 END 消息"))?;
+            let mut sender = WindowSender::new(window, msg_id, 0);
+            for packet in packets {
+                sender.send(stream, packet)?;
+            }
+            sender.finish(stream)?;
+            protocol_utils::send_data_message(stream, &end)?;
+
+            // END 之后客户端回复 ACK，服务端再确认一次
+            protocol_utils::wait_for_ack(stream, msg_id)?;
+            protocol_utils::send_ack_message(stream, msg_id)?;
+        }
+
+        let mut all_end = MessagePacket::new(MessageType::AllEnd, 0, 0, 0);
+        all_end.header.set_message_id(msg_id);
+        protocol_utils::send_data_message(stream, &all_end)?;
+        protocol_utils::wait_for_ack(stream, msg_id)?;
+        protocol_utils::send_ack_message(stream, msg_id)?;
+        println!("[Server] ✓ dump 完成");
+        Ok(())
+    }
+}
+
+/// 确认客户端提出的消息包长度：0（旧版客户端）保持 0，其余限制在 [1024, 64 KiB]
+fn negotiate_packet_size(offered: u32) -> u32 {
+    match offered {
+        0 => 0,
+        size => size.clamp(MAX_MESSAGE_PACKET_SIZE as u32, MAX_PACKET_SIZE),
+    }
+}
+
+/// 处理失败时发给客户端的 ERROR 报告
+///
+/// 连接已中断（I/O 错误、超时）或客户端已经报告了错误时不再回复。
+fn error_report(e: &anyhow::Error) -> Option<ErrorReport> {
+    if let Some(report) = e.downcast_ref::<ErrorReport>() {
+        return Some(report.clone());
+    }
+    if let Some(e) = e.downcast_ref::<ClientError>() {
+        return match e {
+            ClientError::Remote(_) | ClientError::Io(_) | ClientError::Timeout(_) => None,
+            other => Some(other.to_report()),
+        };
+    }
+    // 存储读写失败
+    if let Some(e) = e.downcast_ref::<io::Error>() {
+        let code = match e.kind() {
+            io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded => ErrorCode::StorageFull,
+            _ => ErrorCode::Storage,
+        };
+        return Some(ErrorReport::new(code, e.to_string()));
+    }
+    Some(ErrorReport::new(ErrorCode::Internal, e.to_string()))
+}
@@ -0,0 +1,119 @@
+// src/server/storage.rs
+// 服务端报告存储：每份报告一个文件，按保存顺序命名
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::codec::CodecId;
+use crate::constants;
+
+/// 报告类别，save / dump 命令按类别读写
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportKind {
+    /// SAVE_COMMAND / DUMP_COMMAND
+    System,
+    /// SAVE_PROCESS_COMMAND / DUMP_PROCESS_COMMAND
+    Process,
+}
+
+impl ReportKind {
+    fn dir_name(self) -> &'static str {
+        match self {
+            ReportKind::System => "system",
+            ReportKind::Process => "process",
+        }
+    }
+}
+
+/// 服务端收到的命令
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Save(ReportKind),
+    Dump(ReportKind),
+}
+
+impl Command {
+    /// 由 START 消息 reserved 字段中的命令编号解析
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            constants::SAVE_COMMAND => Some(Command::Save(ReportKind::System)),
+            constants::DUMP_COMMAND => Some(Command::Dump(ReportKind::System)),
+            constants::SAVE_PROCESS_COMMAND => Some(Command::Save(ReportKind::Process)),
+            constants::DUMP_PROCESS_COMMAND => Some(Command::Dump(ReportKind::Process)),
+            _ => None,
+        }
+    }
+}
+
+/// 报告存储目录
+///
+/// 目录结构: `<root>/<system|process>/<毫秒时间戳>-<序号>.<编码>.bin`，文件内容为客户端发送的压缩负载。
+/// 旧版本保存的 `<毫秒时间戳>-<序号>.bin` 视为 zlib。
+pub struct Storage {
+    root: PathBuf,
+    seq: AtomicU64,
+}
+
+impl Storage {
+    pub fn open(root: impl Into<PathBuf>) -> io::Result<Self> {
+        let root = root.into();
+        for kind in [ReportKind::System, ReportKind::Process] {
+            fs::create_dir_all(root.join(kind.dir_name()))?;
+        }
+        Ok(Self { root, seq: AtomicU64::new(0) })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 保存一份报告；先写临时文件再重命名，保证 dump 不会读到写了一半的文件
+    pub fn save(&self, kind: ReportKind, codec: CodecId, payload: &[u8]) -> io::Result<PathBuf> {
+        let dir = self.root.join(kind.dir_name());
+        let name = format!(
+            "{:013}-{:06}",
+            chrono::Utc::now().timestamp_millis(),
+            self.seq.fetch_add(1, Ordering::Relaxed)
+        );
+        let tmp_path = dir.join(format!(".{}.tmp", name));
+        let path = dir.join(format!("{}.{}.bin", name, codec));
+
+        let result = (|| -> io::Result<()> {
+            let mut file = fs::File::create(&tmp_path)?;
+            file.write_all(payload)?;
+            file.sync_all()?;
+            fs::rename(&tmp_path, &path)
+        })();
+        // 写入失败（如存储空间不足）时删除不完整的临时文件
+        if let Err(e) = result {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+        Ok(path)
+    }
+
+    /// 按保存顺序读取某一类别的全部报告及其编码
+    pub fn load_all(&self, kind: ReportKind) -> io::Result<Vec<(CodecId, Vec<u8>)>> {
+        let dir = self.root.join(kind.dir_name());
+        let mut paths: Vec<PathBuf> = fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "bin"))
+            .collect();
+        paths.sort();
+        paths
+            .iter()
+            .map(|path| Ok((codec_of(path)?, fs::read(path)?)))
+            .collect()
+    }
+}
+
+/// 由文件名中的编码部分解析编码，没有编码部分时为 zlib
+fn codec_of(path: &Path) -> io::Result<CodecId> {
+    match Path::new(path.file_stem().unwrap_or_default()).extension() {
+        None => Ok(CodecId::Zlib),
+        Some(ext) => ext
+            .to_str()
+            .and_then(|name| name.parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("未知编码: {}", path.display()))),
+    }
+}
@@ -0,0 +1,406 @@
+// src/spool.rs
+// 本地 spool：保存失败的报告先落盘，服务端恢复后按顺序补发
+//
+// 目录结构:
+//   <seq:020>.seg  分段文件，只追加写入，文件名为段内第一条记录的序列号
+//   cursor         下一条待发送记录的序列号
+//   lock           跨进程互斥
+//
+// 记录格式（大端）:
+//   magic "XBSR"(4) | seq(8) | created_ms(8) | command(1) | len(4) | crc32c(4) | payload(len)
+//   crc32c 覆盖 seq 到 len 的字段以及 payload
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::client::Client;
+use crate::error::{ClientError, Result};
+
+const RECORD_MAGIC: &[u8; 4] = b"XBSR";
+const RECORD_HEADER_SIZE: usize = 29;
+// 单个分段超过该大小后写入新分段
+const SEGMENT_MAX_BYTES: u64 = 1024 * 1024;
+const SEGMENT_EXTENSION: &str = "seg";
+const CURSOR_FILE: &str = "cursor";
+const LOCK_FILE: &str = "lock";
+
+/// 一条待发送的记录
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpoolRecord {
+    pub seq: u64,
+    /// 写入时间，毫秒时间戳
+    pub created_ms: u64,
+    /// 保存命令编号 (SAVE_COMMAND / SAVE_PROCESS_COMMAND)
+    pub command: u8,
+    pub payload: Vec<u8>,
+}
+
+/// 分段文件
+#[derive(Debug, Clone)]
+struct Segment {
+    first_seq: u64,
+    path: PathBuf,
+    size: u64,
+}
+
+/// 本地 spool
+#[derive(Debug)]
+pub struct Spool {
+    dir: PathBuf,
+    max_bytes: u64,
+    max_age: Option<Duration>,
+    // 进程内互斥，跨进程由 lock 文件上的 flock 保证
+    lock: Mutex<()>,
+}
+
+impl Spool {
+    /// 打开（必要时创建）spool 目录；`max_bytes` 为总大小上限，`max_age` 为记录保留时长
+    ///
+    /// 最新分段末尾有不完整或校验失败的记录（写入中途退出）时截断到最后一条有效记录之后，
+    /// 否则之后追加的记录都无法读出。
+    pub fn open(dir: impl Into<PathBuf>, max_bytes: u64, max_age: Option<Duration>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)
+            .map_err(|e| ClientError::Config(format!("创建 spool 目录 {} 失败: {}", dir.display(), e)))?;
+        let spool = Self { dir, max_bytes, max_age, lock: Mutex::new(()) };
+        {
+            let _guard = spool.lock()?;
+            if let Some(mut last) = spool.segments()?.pop() {
+                let valid_len = decode_segment(&fs::read(&last.path)?).1 as u64;
+                truncate_tail(&mut last, valid_len)?;
+            }
+        }
+        Ok(spool)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 追加一条记录，返回其序列号；超过大小上限时丢弃最旧的分段
+    ///
+    /// 单条记录超过大小上限时返回错误，不写入。
+    /// 最新分段末尾的记录不完整时（其他进程写入中途退出）先截断，新记录紧接最后一条完整记录写入。
+    pub fn push(&self, command: u8, payload: &[u8]) -> Result<u64> {
+        let _guard = self.lock()?;
+        let mut segments = self.segments()?;
+        let cursor = self.read_cursor();
+        let seq = match segments.last_mut() {
+            Some(last) => {
+                let (last_seq, valid_len) = scan_headers(&last.path)?;
+                truncate_tail(last, valid_len)?;
+                last_seq.map(|seq| seq + 1).unwrap_or(last.first_seq)
+            }
+            None => cursor,
+        }
+        .max(cursor);
+
+        let record = SpoolRecord { seq, created_ms: now_ms(), command, payload: payload.to_vec() };
+        let bytes = encode_record(&record);
+        let len = bytes.len() as u64;
+        if len > self.max_bytes {
+            return Err(ClientError::Io(io::Error::new(io::ErrorKind::FileTooLarge, format!(
+                "记录 {} 字节超过 spool 大小上限 {} 字节", len, self.max_bytes
+            ))));
+        }
+
+        // 追加后当前分段会超过分段大小或总大小上限时从该记录开始新分段，
+        // 最新的分段不超过上限，丢弃较旧的分段后总大小总能回到上限以内
+        let segment_limit = SEGMENT_MAX_BYTES.min(self.max_bytes);
+        let path = match segments.last() {
+            Some(last) if last.size == 0 || last.size + len <= segment_limit => last.path.clone(),
+            _ => {
+                let segment = Segment { first_seq: seq, path: self.segment_path(seq), size: 0 };
+                segments.push(segment.clone());
+                segment.path
+            }
+        };
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        file.write_all(&bytes)?;
+        file.sync_data()?;
+        if let Some(last) = segments.last_mut() {
+            last.size += len;
+        }
+
+        self.enforce_caps(&segments)?;
+        Ok(seq)
+    }
+
+    /// 列出所有待发送的记录（不含过期记录）
+    pub fn list(&self) -> Result<Vec<SpoolRecord>> {
+        let _guard = self.lock()?;
+        self.pending()
+    }
+
+    /// 是否没有待发送的记录
+    ///
+    /// 只比较游标与最新分段中最后一条记录的序列号，不读取负载；已过期但尚未清理的记录仍视为待发送。
+    pub fn is_empty(&self) -> Result<bool> {
+        let _guard = self.lock()?;
+        let Some(last) = self.segments()?.pop() else { return Ok(true) };
+        let cursor = self.read_cursor();
+        Ok(match scan_headers(&last.path)?.0 {
+            Some(seq) => seq < cursor,
+            // 空分段：之前的分段最多到 first_seq - 1
+            None => last.first_seq <= cursor,
+        })
+    }
+
+    /// 按顺序发送待发送的记录，遇到第一个失败即停止，返回成功发送的条数
+    ///
+    /// 每条记录发送成功后立即推进游标，中途退出最多重发一条。
+    pub fn flush<F>(&self, mut send: F) -> Result<usize>
+    where
+        F: FnMut(&SpoolRecord) -> Result<()>,
+    {
+        let _guard = self.lock()?;
+        let mut sent = 0;
+        let mut result = Ok(());
+        for record in self.pending()? {
+            if let Err(e) = send(&record) {
+                result = Err(e);
+                break;
+            }
+            self.write_cursor(record.seq + 1)?;
+            sent += 1;
+        }
+        self.remove_flushed()?;
+        result.map(|_| sent)
+    }
+
+    /// 删除全部记录，返回删除的待发送记录数
+    pub fn purge(&self) -> Result<usize> {
+        let _guard = self.lock()?;
+        let count = self.pending()?.len();
+        let next = self.segments()?
+            .iter()
+            .filter_map(|s| read_records(&s.path).last().map(|r| r.seq + 1))
+            .max()
+            .unwrap_or(0)
+            .max(self.read_cursor());
+        for segment in self.segments()? {
+            fs::remove_file(&segment.path)?;
+        }
+        // 游标保持递增，避免新记录与旧记录序列号重复
+        self.write_cursor(next)?;
+        Ok(count)
+    }
+
+    /// 游标之后且未过期的记录
+    fn pending(&self) -> Result<Vec<SpoolRecord>> {
+        let cursor = self.read_cursor();
+        let oldest = self.oldest_ms();
+        Ok(self
+            .segments()?
+            .iter()
+            .flat_map(|s| read_records(&s.path))
+            .filter(|r| r.seq >= cursor)
+            .filter(|r| oldest.is_none_or(|t| r.created_ms >= t))
+            .collect())
+    }
+
+    /// 超过大小上限时删除最旧的分段，删除所有记录都已过期的分段
+    fn enforce_caps(&self, segments: &[Segment]) -> Result<()> {
+        let mut total: u64 = segments.iter().map(|s| s.size).sum();
+        let oldest = self.oldest_ms();
+        // 始终保留最新的分段
+        for segment in segments.iter().take(segments.len().saturating_sub(1)) {
+            let expired = oldest.is_some_and(|t| read_records(&segment.path).iter().all(|r| r.created_ms < t));
+            if total <= self.max_bytes && !expired {
+                break;
+            }
+            eprintln!("[Spool] ✗ {} 超出{}，已丢弃", segment.path.display(), if expired { "保留时长" } else { "大小上限" });
+            fs::remove_file(&segment.path)?;
+            total -= segment.size;
+        }
+        Ok(())
+    }
+
+    /// 删除所有记录都已发送或已过期的分段
+    fn remove_flushed(&self) -> Result<()> {
+        let cursor = self.read_cursor();
+        let oldest = self.oldest_ms();
+        for segment in self.segments()? {
+            if read_records(&segment.path).iter().all(|r| r.seq < cursor || oldest.is_some_and(|t| r.created_ms < t)) {
+                fs::remove_file(&segment.path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 早于该时间写入的记录已过期
+    fn oldest_ms(&self) -> Option<u64> {
+        self.max_age.map(|age| now_ms().saturating_sub(age.as_millis() as u64))
+    }
+
+    /// 按第一条记录的序列号排序的分段
+    fn segments(&self) -> Result<Vec<Segment>> {
+        let mut segments = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_EXTENSION) {
+                continue;
+            }
+            let Some(first_seq) = path.file_stem().and_then(|s| s.to_str()).and_then(|s| s.parse().ok()) else {
+                continue;
+            };
+            let size = fs::metadata(&path)?.len();
+            segments.push(Segment { first_seq, path, size });
+        }
+        segments.sort_by_key(|s| s.first_seq);
+        Ok(segments)
+    }
+
+    fn segment_path(&self, first_seq: u64) -> PathBuf {
+        self.dir.join(format!("{:020}.{}", first_seq, SEGMENT_EXTENSION))
+    }
+
+    fn read_cursor(&self) -> u64 {
+        fs::read_to_string(self.dir.join(CURSOR_FILE))
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+            .unwrap_or(0)
+    }
+
+    /// 先写临时文件再重命名，避免中途退出留下损坏的游标
+    fn write_cursor(&self, next_seq: u64) -> Result<()> {
+        let tmp = self.dir.join(format!("{}.tmp", CURSOR_FILE));
+        fs::write(&tmp, next_seq.to_string())?;
+        fs::rename(&tmp, self.dir.join(CURSOR_FILE))?;
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<SpoolLock<'_>> {
+        let guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let file = OpenOptions::new().create(true).truncate(false).write(true).open(self.dir.join(LOCK_FILE))?;
+        // SAFETY: fd 在 file 存活期间有效；文件关闭时锁自动释放
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(SpoolLock { _file: file, _guard: guard })
+    }
+}
+
+/// 持有期间独占 spool 目录
+struct SpoolLock<'a> {
+    _file: File,
+    _guard: std::sync::MutexGuard<'a, ()>,
+}
+
+/// 启动后台线程，每隔 `interval` 补发一次，`stop` 置位后退出
+pub fn spawn_flusher(client: Arc<Client>, interval: Duration, stop: Arc<AtomicBool>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        while !stop.load(Ordering::Relaxed) {
+            match client.flush_spool() {
+                Ok(0) => {}
+                Ok(n) => println!("[Spool] ✓ 已补发 {} 条记录", n),
+                Err(e) => eprintln!("[Spool] ✗ 补发失败，稍后重试: {}", e),
+            }
+            // 分段睡眠以便及时响应退出
+            let deadline = std::time::Instant::now() + interval;
+            while !stop.load(Ordering::Relaxed) && std::time::Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(200).min(interval));
+            }
+        }
+    })
+}
+
+fn encode_record(record: &SpoolRecord) -> Vec<u8> {
+    let mut body = Vec::with_capacity(RECORD_HEADER_SIZE - 8 + record.payload.len());
+    body.extend_from_slice(&record.seq.to_be_bytes());
+    body.extend_from_slice(&record.created_ms.to_be_bytes());
+    body.push(record.command);
+    body.extend_from_slice(&(record.payload.len() as u32).to_be_bytes());
+    let checksum = crc32c::crc32c_append(crc32c::crc32c(&body), &record.payload);
+
+    let mut bytes = Vec::with_capacity(RECORD_HEADER_SIZE + record.payload.len());
+    bytes.extend_from_slice(RECORD_MAGIC);
+    bytes.extend_from_slice(&body);
+    bytes.extend_from_slice(&checksum.to_be_bytes());
+    bytes.extend_from_slice(&record.payload);
+    bytes
+}
+
+/// 读取分段中的记录，遇到不完整或校验失败的记录时停止（之后的数据无法定位）
+fn read_records(path: &Path) -> Vec<SpoolRecord> {
+    let Ok(data) = fs::read(path) else { return Vec::new() };
+    let (records, valid_len) = decode_segment(&data);
+    if valid_len < data.len() {
+        eprintln!("[Spool] ✗ {} 在偏移 {} 处损坏，忽略其后的数据", path.display(), valid_len);
+    }
+    records
+}
+
+/// 依次解码分段数据，返回 (有效记录, 最后一条有效记录的结束偏移)
+fn decode_segment(data: &[u8]) -> (Vec<SpoolRecord>, usize) {
+    let mut records = Vec::new();
+    let mut offset = 0;
+    while let Some((record, len)) = decode_record(&data[offset..]) {
+        records.push(record);
+        offset += len;
+    }
+    (records, offset)
+}
+
+/// 只读取记录头、跳过负载扫描分段，不校验 crc32c
+///
+/// 返回 (最后一条完整记录的序列号, 其结束偏移)；不完整的记录（写入中途退出）不计入。
+fn scan_headers(path: &Path) -> Result<(Option<u64>, u64)> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut header = [0u8; RECORD_HEADER_SIZE];
+    let mut offset = 0u64;
+    let mut last = None;
+    while offset + RECORD_HEADER_SIZE as u64 <= size {
+        file.read_exact(&mut header)?;
+        let len = u32::from_be_bytes(header[21..25].try_into().unwrap_or_default()) as u64;
+        let end = offset + RECORD_HEADER_SIZE as u64 + len;
+        if &header[..4] != RECORD_MAGIC || end > size {
+            break;
+        }
+        last = Some(u64::from_be_bytes(header[4..12].try_into().unwrap_or_default()));
+        offset = file.seek(SeekFrom::Start(end))?;
+    }
+    Ok((last, offset))
+}
+
+/// 把分段截断到 `valid_len`，丢弃末尾无法读出的数据
+fn truncate_tail(segment: &mut Segment, valid_len: u64) -> Result<()> {
+    if valid_len >= segment.size {
+        return Ok(());
+    }
+    eprintln!(
+        "[Spool] ✗ {} 末尾 {} 字节不完整或已损坏，截断到偏移 {}",
+        segment.path.display(), segment.size - valid_len, valid_len
+    );
+    let file = OpenOptions::new().write(true).open(&segment.path)?;
+    file.set_len(valid_len)?;
+    file.sync_data()?;
+    segment.size = valid_len;
+    Ok(())
+}
+
+fn decode_record(data: &[u8]) -> Option<(SpoolRecord, usize)> {
+    if data.len() < RECORD_HEADER_SIZE || &data[..4] != RECORD_MAGIC {
+        return None;
+    }
+    let u64_at = |i: usize| u64::from_be_bytes(data[i..i + 8].try_into().unwrap_or_default());
+    let u32_at = |i: usize| u32::from_be_bytes(data[i..i + 4].try_into().unwrap_or_default());
+    let len = u32_at(21) as usize;
+    let payload = data.get(RECORD_HEADER_SIZE..RECORD_HEADER_SIZE + len)?;
+    let checksum = crc32c::crc32c_append(crc32c::crc32c(&data[4..25]), payload);
+    if checksum != u32_at(25) {
+        return None;
+    }
+    let record = SpoolRecord { seq: u64_at(4), created_ms: u64_at(12), command: data[20], payload: payload.to_vec() };
+    Some((record, RECORD_HEADER_SIZE + len))
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
@@ -0,0 +1,286 @@
+// src/transport.rs
+// 传输层抽象：协议可以运行在 vsock、Unix socket 或 TCP 之上
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::fs;
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::os::fd::{FromRawFd, IntoRawFd, OwnedFd};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use vsock::{VsockListener, VsockStream, VsockAddr};
+use crate::error::ClientError;
+
+/// 协议所需的双向字节流
+pub trait Transport: Read + Write + Send {
+    fn shutdown(&self, how: Shutdown) -> io::Result<()>;
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl Transport for VsockStream {
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        VsockStream::shutdown(self, how)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        VsockStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        VsockStream::set_write_timeout(self, timeout)
+    }
+}
+
+impl Transport for UnixStream {
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        UnixStream::shutdown(self, how)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_write_timeout(self, timeout)
+    }
+}
+
+impl Transport for TcpStream {
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        TcpStream::shutdown(self, how)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_write_timeout(self, timeout)
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        (**self).shutdown(how)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        (**self).set_read_timeout(timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        (**self).set_write_timeout(timeout)
+    }
+}
+
+/// 服务端地址
+///
+/// 支持 `vsock://3:1234`、`unix:///run/xbox.sock`、`tcp://127.0.0.1:1234` 三种写法。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    Vsock { cid: u32, port: u32 },
+    Unix(PathBuf),
+    Tcp(String),
+}
+
+impl Endpoint {
+    /// 建立连接，`timeout` 为 None 时一直阻塞
+    pub fn connect(&self, timeout: Option<Duration>) -> io::Result<Box<dyn Transport>> {
+        match self {
+            Endpoint::Vsock { cid, port } => Ok(Box::new(connect_vsock(*cid, *port, timeout)?)),
+            // 本地 Unix socket 连接不会长时间阻塞，不单独设置超时
+            Endpoint::Unix(path) => Ok(Box::new(UnixStream::connect(path)?)),
+            Endpoint::Tcp(addr) => Ok(Box::new(connect_tcp(addr, timeout)?)),
+        }
+    }
+}
+
+impl Endpoint {
+    /// 在该地址上监听（服务端使用）
+    ///
+    /// Unix socket 文件已存在时先删除，避免上次异常退出留下的文件导致绑定失败；
+    /// 该路径上是其他类型的文件时返回 `AddrInUse`，不删除（地址写错时不能误删普通文件）。
+    pub fn bind(&self) -> io::Result<Listener> {
+        match self {
+            Endpoint::Vsock { cid, port } => {
+                Ok(Listener::Vsock(VsockListener::bind(&VsockAddr::new(*cid, *port))?))
+            }
+            Endpoint::Unix(path) => {
+                match fs::symlink_metadata(path) {
+                    Ok(meta) if meta.file_type().is_socket() => fs::remove_file(path)?,
+                    Ok(_) => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::AddrInUse,
+                            format!("{} 已存在且不是 socket 文件", path.display()),
+                        ));
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(e),
+                }
+                Ok(Listener::Unix(UnixListener::bind(path)?))
+            }
+            Endpoint::Tcp(addr) => Ok(Listener::Tcp(TcpListener::bind(addr)?)),
+        }
+    }
+}
+
+/// 监听中的服务端 socket
+pub enum Listener {
+    Vsock(VsockListener),
+    Unix(UnixListener),
+    Tcp(TcpListener),
+}
+
+impl Listener {
+    /// 等待并接受一个连接，返回连接和对端地址描述
+    pub fn accept(&self) -> io::Result<(Box<dyn Transport>, String)> {
+        match self {
+            Listener::Vsock(listener) => {
+                let (stream, addr) = listener.accept()?;
+                Ok((Box::new(stream), format!("vsock://{}:{}", addr.cid(), addr.port())))
+            }
+            Listener::Unix(listener) => {
+                let (stream, _) = listener.accept()?;
+                Ok((Box::new(stream), "unix".to_string()))
+            }
+            Listener::Tcp(listener) => {
+                let (stream, addr) = listener.accept()?;
+                Ok((Box::new(stream), format!("tcp://{}", addr)))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::Vsock { cid, port } => write!(f, "vsock://{}:{}", cid, port),
+            Endpoint::Unix(path) => write!(f, "unix://{}", path.display()),
+            Endpoint::Tcp(addr) => write!(f, "tcp://{}", addr),
+        }
+    }
+}
+
+impl FromStr for Endpoint {
+    type Err = ClientError;
+
+    fn from_str(s: &str) -> Result<Self, ClientError> {
+        let invalid = || ClientError::Config(format!("无效的服务端地址: {}", s));
+        let (scheme, rest) = s.split_once("://").ok_or_else(invalid)?;
+        match scheme {
+            "vsock" => {
+                let (cid, port) = rest.split_once(':').ok_or_else(invalid)?;
+                Ok(Endpoint::Vsock {
+                    cid: cid.parse().map_err(|_| invalid())?,
+                    port: port.parse().map_err(|_| invalid())?,
+                })
+            }
+            "unix" if !rest.is_empty() => Ok(Endpoint::Unix(PathBuf::from(rest))),
+            "tcp" if rest.contains(':') => Ok(Endpoint::Tcp(rest.to_string())),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// 依次尝试解析出的每个地址
+fn connect_tcp(addr: &str, timeout: Option<Duration>) -> io::Result<TcpStream> {
+    let mut last_err = io::Error::new(io::ErrorKind::InvalidInput, format!("无法解析地址: {}", addr));
+    for socket_addr in addr.to_socket_addrs()? {
+        let result = match timeout {
+            Some(t) => TcpStream::connect_timeout(&socket_addr, t),
+            None => TcpStream::connect(socket_addr),
+        };
+        match result {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_err = e,
+        }
+    }
+    Err(last_err)
+}
+
+/// 带超时的 vsock 连接
+///
+/// vsock 没有提供 connect_timeout，这里以非阻塞方式发起连接，再用 poll 等待连接完成。
+pub fn connect_vsock(cid: u32, port: u32, timeout: Option<Duration>) -> io::Result<VsockStream> {
+    let Some(timeout) = timeout else {
+        return VsockStream::connect(&VsockAddr::new(cid, port));
+    };
+
+    // SAFETY: 仅调用 libc 的 socket 相关函数，fd 由 OwnedFd 管理，出错时自动关闭
+    unsafe {
+        let fd = libc::socket(libc::AF_VSOCK, libc::SOCK_STREAM | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK, 0);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let socket = OwnedFd::from_raw_fd(fd);
+
+        let mut addr: libc::sockaddr_vm = mem::zeroed();
+        addr.svm_family = libc::AF_VSOCK as libc::sa_family_t;
+        addr.svm_cid = cid;
+        addr.svm_port = port;
+        let ret = libc::connect(
+            fd,
+            &addr as *const libc::sockaddr_vm as *const libc::sockaddr,
+            mem::size_of::<libc::sockaddr_vm>() as libc::socklen_t,
+        );
+        if ret < 0 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() != Some(libc::EINPROGRESS) {
+                return Err(err);
+            }
+            wait_writable(fd, timeout)?;
+
+            // 连接结果通过 SO_ERROR 返回
+            let mut so_error: libc::c_int = 0;
+            let mut len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+            if libc::getsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_ERROR,
+                &mut so_error as *mut libc::c_int as *mut libc::c_void,
+                &mut len,
+            ) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if so_error != 0 {
+                return Err(io::Error::from_raw_os_error(so_error));
+            }
+        }
+
+        // 恢复阻塞模式，后续读写超时由 set_read_timeout / set_write_timeout 控制
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        if flags < 0 || libc::fcntl(fd, libc::F_SETFL, flags & !libc::O_NONBLOCK) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(VsockStream::from_raw_fd(socket.into_raw_fd()))
+    }
+}
+
+/// 等待 fd 可写，超时返回 TimedOut
+fn wait_writable(fd: libc::c_int, timeout: Duration) -> io::Result<()> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "连接超时"));
+        }
+        let mut pfd = libc::pollfd { fd, events: libc::POLLOUT, revents: 0 };
+        let ms = remaining.as_millis().clamp(1, i32::MAX as u128) as libc::c_int;
+        // SAFETY: pfd 在调用期间有效
+        let n = unsafe { libc::poll(&mut pfd, 1, ms) };
+        if n > 0 {
+            return Ok(());
+        }
+        if n < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+}
+
@@ -1,15 +1,92 @@
 // src/utils.rs
-use vsock::VsockStream;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+use std::time::Instant;
 use crate::constants;
+use crate::config::{ClientConfig, RetryPolicy};
+use crate::error::{ClientError, Result};
+use crate::protocol::FramedStream;
+use crate::transport::Transport;
 
 // 发送 shutdown 请求
-pub fn graceful_shutdown(stream: &mut VsockStream, log_prefix: &str) {
+pub fn graceful_shutdown<T: Transport + ?Sized>(stream: &mut T, log_prefix: &str) {
     // 主动关闭连接
     if let Err(e) = stream.shutdown(std::net::Shutdown::Both) {
         eprintln!("{} ✗ 关闭连接失败: {:?}", log_prefix, e);
     }
 }
 
+/// 按配置连接服务端
+///
+/// 连接成功后按配置设置握手阶段的读超时、写超时以及传输总时长。
+pub fn connect(config: &ClientConfig, log_prefix: &str) -> Result<FramedStream> {
+    match config.endpoint.connect(config.timeouts.connect) {
+        Ok(stream) => {
+            println!("{} ✓ 已连接到服务端 {}", log_prefix, config.endpoint);
+            let mut framed = FramedStream::with_version(stream, config.protocol_version);
+            framed.set_deadline(config.timeouts.transfer.map(|t| Instant::now() + t));
+            framed.set_read_timeout(config.timeouts.handshake);
+            framed.set_write_timeout(config.timeouts.ack)?;
+            Ok(framed)
+        }
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+            eprintln!("{} ✗ 连接超时: {:?}", log_prefix, e);
+            Err(ClientError::Timeout(format!(
+                "连接 {} 超过 {:?}", config.endpoint, config.timeouts.connect.unwrap_or_default()
+            )))
+        }
+        Err(e) => {
+            eprintln!("{} ✗ 连接失败: {:?}", log_prefix, e);
+            Err(ClientError::Connect(e))
+        }
+    }
+}
+
+/// 按重试策略执行 `op`，仅对可重试的错误（连接失败、超时、连接中断）重试
+pub fn with_retry<T>(policy: &RetryPolicy, log_prefix: &str, mut op: impl FnMut(u32) -> Result<T>) -> Result<T> {
+    let mut attempt = 1;
+    loop {
+        match op(attempt) {
+            Err(e) if e.is_retryable() && attempt < policy.max_attempts => {
+                let delay = policy.backoff_for(attempt);
+                eprintln!("{} ✗ 第 {} 次尝试失败: {}, {:?} 后重试", log_prefix, attempt, e, delay);
+                thread::sleep(delay);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// 进程内随机数，用于退避抖动和传输 ID
+///
+/// 标准库的 RandomState 每次创建都会使用不同的随机密钥，足够满足这里的需求。
+pub fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+// message_id 分配：进程内随机基数 + 递增计数
+static MESSAGE_ID_BASE: OnceLock<u32> = OnceLock::new();
+static MESSAGE_ID_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// 分配一个 message_id
+///
+/// 同一进程内依次递增，不同进程从不同的随机基数开始，避免多个客户端的传输在服务端混淆。
+/// 0 保留不用。
+pub fn next_message_id() -> u32 {
+    let base = *MESSAGE_ID_BASE.get_or_init(|| random_u64() as u32);
+    loop {
+        let id = base.wrapping_add(MESSAGE_ID_COUNTER.fetch_add(1, Ordering::Relaxed));
+        if id != 0 {
+            return id;
+        }
+    }
+}
+
 pub fn get_command_code(command: &str) -> u8 {
     match command {
         constants::SAVE => constants::SAVE_COMMAND,
@@ -0,0 +1,87 @@
+// tests/codec.rs
+// 写入端解码：压缩数据分段写入，解出的内容应与原文一致；lz4 应逐块输出而不是等到 finish
+use std::cell::RefCell;
+use std::io::{self, Read, Write};
+use std::rc::Rc;
+use xbox_client::CodecId;
+
+/// 可在 finish 之前查看已写出内容的 writer
+#[derive(Clone, Default)]
+struct Shared(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 不易压缩的测试数据，保证 lz4 产生多个数据块
+fn sample(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491_u32;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state % 40) as u8 + b'0'
+        })
+        .collect()
+}
+
+fn encode(id: CodecId, data: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    id.codec().encoder(Box::new(data), 6).unwrap().read_to_end(&mut compressed).unwrap();
+    compressed
+}
+
+#[test]
+fn decoders_accept_arbitrary_write_sizes() {
+    let data = sample(300 * 1024);
+    for id in CodecId::ALL {
+        let compressed = encode(id, &data);
+        for piece in [1, 7, 4096, compressed.len()] {
+            let out = Shared::default();
+            let mut decoder = id.codec().decoder(Box::new(out.clone())).unwrap();
+            for chunk in compressed.chunks(piece) {
+                decoder.write_all(chunk).unwrap();
+            }
+            decoder.finish().unwrap();
+            assert!(*out.0.borrow() == data, "{} 按 {} 字节写入时解码结果不一致", id, piece);
+        }
+    }
+}
+
+#[test]
+fn lz4_decoder_streams_block_by_block() {
+    let data = sample(1024 * 1024);
+    let compressed = encode(CodecId::Lz4, &data);
+
+    let out = Shared::default();
+    let mut decoder = CodecId::Lz4.codec().decoder(Box::new(out.clone())).unwrap();
+    let half = compressed.len() / 2;
+    decoder.write_all(&compressed[..half]).unwrap();
+    // 前一半压缩数据中完整的数据块已经解出
+    let produced = out.0.borrow().len();
+    assert!(produced > 0 && produced < data.len(), "写入一半后解出 {} 字节", produced);
+    assert_eq!(out.0.borrow()[..], data[..produced]);
+
+    decoder.write_all(&compressed[half..]).unwrap();
+    decoder.finish().unwrap();
+    assert!(*out.0.borrow() == data);
+}
+
+#[test]
+fn lz4_decoder_rejects_truncated_input() {
+    let compressed = encode(CodecId::Lz4, &sample(200 * 1024));
+    let mut decoder = CodecId::Lz4.codec().decoder(Box::new(io::sink())).unwrap();
+    decoder.write_all(&compressed[..compressed.len() - 3]).unwrap();
+    assert!(decoder.finish().is_err());
+
+    let mut decoder = CodecId::Lz4.codec().decoder(Box::new(io::sink())).unwrap();
+    assert!(decoder.write_all(b"not an lz4 frame").is_err());
+}
@@ -0,0 +1,140 @@
+// tests/collector_system.rs
+// 系统采集：读取 tests/fixtures/procfs 下伪造的 procfs，检查解析结果和 top 格式输出
+use std::fs;
+use std::path::{Path, PathBuf};
+use xbox_client::collector::system::{self, CpuTimes, MemInfo, SwapInfo};
+use xbox_client::collector::Collector;
+
+fn fixture_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/procfs")
+}
+
+/// 在临时目录中生成只包含 `files` 的 procfs
+fn fake_root(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("xbox-procfs-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&root);
+    for (path, content) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    root
+}
+
+#[test]
+fn read_cpu_times_parses_summary_line() {
+    let cpu = system::read_cpu_times(&fixture_root()).unwrap();
+    assert_eq!(cpu, CpuTimes {
+        user: 10000,
+        nice: 500,
+        system: 3000,
+        idle: 85000,
+        iowait: 1000,
+        irq: 200,
+        softirq: 300,
+        steal: 0,
+    });
+    assert_eq!(cpu.total(), 100000);
+}
+
+#[test]
+fn read_cpu_times_accepts_old_kernels_and_rejects_missing_line() {
+    // 2.4 内核只有前 4 个字段
+    let root = fake_root("cpu-short", &[("stat", "cpu  1 2 3 4\n")]);
+    let cpu = system::read_cpu_times(&root).unwrap();
+    assert_eq!(cpu, CpuTimes { user: 1, nice: 2, system: 3, idle: 4, ..CpuTimes::default() });
+
+    let root = fake_root("cpu-missing", &[("stat", "cpu0 1 2 3 4\nctxt 5\n")]);
+    assert!(system::read_cpu_times(&root).is_err());
+
+    let root = fake_root("cpu-fields", &[("stat", "cpu  1 2 3\n")]);
+    assert!(system::read_cpu_times(&root).is_err());
+}
+
+#[test]
+fn read_meminfo_picks_needed_fields() {
+    let mem = system::read_meminfo(&fixture_root()).unwrap();
+    assert_eq!(mem, MemInfo {
+        total: 8192000,
+        free: 2048000,
+        available: 4096000,
+        buffers: 102400,
+        cached: 1024000,
+        s_reclaimable: 204800,
+    });
+    assert_eq!(mem.used(), 4096000);
+    assert_eq!(mem.buff_cache(), 1331200);
+
+    let root = fake_root("meminfo-no-total", &[("meminfo", "MemFree: 1024 kB\n")]);
+    assert!(system::read_meminfo(&root).is_err());
+}
+
+#[test]
+fn read_swaps_sums_all_devices() {
+    let swap = system::read_swaps(&fixture_root()).unwrap();
+    assert_eq!(swap, SwapInfo { total: 3145728, used: 524288 });
+
+    // 没有交换分区时只有表头
+    let root = fake_root("swaps-empty", &[("swaps", "Filename\tType\tSize\tUsed\tPriority\n")]);
+    assert_eq!(system::read_swaps(&root).unwrap(), SwapInfo::default());
+}
+
+#[test]
+fn read_ip_addresses_from_fib_trie() {
+    // 每个地址在 Main / Local 表中各出现一次，只取一次；排除回环地址和非 LOCAL 路由
+    let ips = system::read_ip_addresses(&fixture_root()).unwrap();
+    assert_eq!(ips, vec!["10.0.2.15".to_string(), "172.17.0.1".to_string()]);
+
+    assert_eq!(system::read_hostname(&fixture_root()).unwrap(), "blackbox-guest");
+}
+
+#[test]
+fn format_cpu_since_boot_and_between_samples() {
+    let cpu = system::read_cpu_times(&fixture_root()).unwrap();
+    assert_eq!(
+        system::format_cpu(None, &cpu),
+        "%Cpu(s): 10.0 us,  3.0 sy,  0.5 ni, 85.0 id,  1.0 wa,  0.2 hi,  0.3 si,  0.0 st"
+    );
+
+    let next = CpuTimes { user: cpu.user + 50, system: cpu.system + 25, idle: cpu.idle + 25, ..cpu };
+    assert_eq!(
+        system::format_cpu(Some(&cpu), &next),
+        "%Cpu(s): 50.0 us, 25.0 sy,  0.0 ni, 25.0 id,  0.0 wa,  0.0 hi,  0.0 si,  0.0 st"
+    );
+
+    // 两次采样之间没有变化时不除以 0
+    assert_eq!(
+        system::format_cpu(Some(&cpu), &cpu),
+        "%Cpu(s):  0.0 us,  0.0 sy,  0.0 ni,  0.0 id,  0.0 wa,  0.0 hi,  0.0 si,  0.0 st"
+    );
+}
+
+#[test]
+fn format_memory_and_swap_match_top() {
+    let mem = system::read_meminfo(&fixture_root()).unwrap();
+    let swap = system::read_swaps(&fixture_root()).unwrap();
+    assert_eq!(
+        system::format_memory(&mem),
+        "MiB Mem :   8000.0 total,   2000.0 free,   4000.0 used,   1300.0 buff/cache"
+    );
+    assert_eq!(
+        system::format_swap(&swap, &mem),
+        "MiB Swap:   3072.0 total,   2560.0 free,    512.0 used.   4000.0 avail Mem"
+    );
+}
+
+#[test]
+fn collector_builds_snapshot_from_fake_root() {
+    let mut collector = Collector::with_root(fixture_root());
+    let snapshot = collector.collect().unwrap();
+    assert_eq!(snapshot.hostname, "blackbox-guest");
+    assert_eq!(snapshot.ip_address, "10.0.2.15");
+    assert!(snapshot.system_metrics.cpu_info.starts_with("%Cpu(s): 10.0 us"));
+    assert!(snapshot.system_metrics.memory_info.starts_with("MiB Mem :   8000.0 total"));
+    assert!(snapshot.system_metrics.swap_info.starts_with("MiB Swap:   3072.0 total"));
+    assert!(snapshot.logs.dmesg.is_empty());
+
+    // 第二次采样按区间计算，数据未变化时全部为 0
+    let snapshot = collector.collect().unwrap();
+    assert!(snapshot.system_metrics.cpu_info.starts_with("%Cpu(s):  0.0 us"));
+}
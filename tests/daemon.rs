@@ -0,0 +1,81 @@
+// tests/daemon.rs
+// 守护进程的批次报告：由伪造的 procfs 和录制的内核日志采样，经参考服务端保存后能按 Report 取回
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use xbox_client::collector::kmsg::KmsgReader;
+use xbox_client::collector::Collector;
+use xbox_client::daemon;
+use xbox_client::server::{Server, Storage};
+use xbox_client::{Client, ClientConfig, Endpoint, RetryPolicy};
+
+fn fixture(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(path)
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("xbox-daemon-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// 采集器：procfs 指向 fixtures，内核日志读取录制文件的副本
+fn collector(dir: &Path) -> Collector {
+    fs::copy(fixture("kmsg/recorded"), dir.join("kmsg")).unwrap();
+    fs::copy(fixture("kmsg/boot_id"), dir.join("boot_id")).unwrap();
+    let kmsg = KmsgReader::new(dir.join("kmsg"), dir.join("boot_id"), dir.join("kmsg_cursor.json"));
+    Collector::with_root(fixture("procfs")).with_kmsg(kmsg)
+}
+
+#[test]
+fn batch_report_maps_samples_to_report() {
+    let dir = temp_dir("batch");
+    let mut collector = collector(&dir);
+    let samples = vec![collector.collect().unwrap(), collector.collect().unwrap()];
+
+    let report = daemon::batch_report(&samples);
+    assert_eq!(report.servers.len(), 1);
+    let server = &report.servers[0];
+    assert_eq!(server.server_id, "blackbox-guest");
+    assert_eq!(server.server_ip, "10.0.2.15");
+
+    // 每个采样一条指标：第一次按开机以来计算，第二次区间内没有变化
+    let usage: Vec<(f64, f64)> = server.system_metrics.iter().map(|m| (m.cpu_usage, m.memory_usage)).collect();
+    assert_eq!(usage, vec![(14.0, 50.0), (0.0, 50.0)]);
+    assert!(server.system_metrics.iter().all(|m| m.timestamp > 0));
+
+    // 只有 err 级别的一条内核日志，且只在第一次采样中出现
+    assert_eq!(server.crash_logs.len(), 1);
+    let crash = &server.crash_logs[0];
+    assert_eq!((crash.id, crash.severity.as_str(), crash.title.as_str()), (4, "high", "EXT4-fs (vda1)"));
+    assert_eq!(crash.message, "EXT4-fs (vda1): error loading journal");
+    assert_eq!(crash.timestamp, server.system_metrics[0].timestamp);
+
+    assert!(daemon::batch_report(&[]).servers.is_empty());
+}
+
+#[test]
+fn batch_report_round_trips_through_dump_reports() {
+    let dir = temp_dir("round-trip");
+    let socket = dir.join("server.sock");
+    let storage = Storage::open(dir.join("storage")).unwrap();
+    let server = Server::bind(Endpoint::Unix(socket.clone()), storage).unwrap();
+    thread::spawn(move || server.run());
+    thread::sleep(Duration::from_millis(100));
+
+    let config = ClientConfig::builder()
+        .endpoint(Endpoint::Unix(socket))
+        .retry(RetryPolicy { max_attempts: 1, ..RetryPolicy::default() })
+        .build()
+        .unwrap();
+    let client = Client::new(config);
+
+    let mut collector = collector(&dir);
+    let report = daemon::batch_report(&[collector.collect().unwrap()]);
+    client.save_report(&report).unwrap();
+
+    // 服务端保存的批次按 Report 解析后与发送的内容一致，没有丢失采样
+    assert_eq!(client.dump_reports().unwrap(), vec![report]);
+}
@@ -0,0 +1,36 @@
+// tests/endpoint.rs
+// Unix socket 监听：只替换上次留下的 socket 文件，不删除同名的其他文件
+use std::fs;
+use std::io;
+use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
+use xbox_client::Endpoint;
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("xbox-endpoint-{}-{}", std::process::id(), name));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn bind_replaces_stale_socket() {
+    let path = temp_path("stale.sock");
+    // 上次异常退出留下的 socket 文件
+    drop(UnixListener::bind(&path).unwrap());
+    assert!(path.exists());
+
+    let listener = Endpoint::Unix(path.clone()).bind().unwrap();
+    drop(listener);
+    let _ = fs::remove_file(path);
+}
+
+#[test]
+fn bind_refuses_to_remove_regular_file() {
+    let path = temp_path("report.json");
+    fs::write(&path, "{}").unwrap();
+
+    let err = Endpoint::Unix(path.clone()).bind().err().expect("不应绑定到普通文件");
+    assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "{}");
+    let _ = fs::remove_file(path);
+}
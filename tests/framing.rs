@@ -0,0 +1,132 @@
+// tests/framing.rs
+// 分帧读取：对端的一次写入可能被拆成多次读取，也可能与下一个消息包合并在一次读取中返回，
+// v1 (20 字节) 与 v2 (28 字节) 消息头都应还原出原样的消息包
+use std::io::{self, Read, Write};
+use std::net::Shutdown;
+use std::time::Duration;
+use xbox_client::protocol::consts::{MAX_MESSAGE_BODY_SIZE, PROTOCOL_VERSION_V1, PROTOCOL_VERSION_V2};
+use xbox_client::protocol::{FramedStream, MessagePacket, MessageType};
+use xbox_client::{data_process, ClientError, Transport};
+
+/// 按 `sizes` 依次（循环）决定每次 read 返回的字节数的连接
+struct Scripted {
+    data: Vec<u8>,
+    pos: usize,
+    sizes: Vec<usize>,
+    reads: usize,
+}
+
+impl Scripted {
+    fn new(data: Vec<u8>, sizes: &[usize]) -> Self {
+        Self { data, pos: 0, sizes: sizes.to_vec(), reads: 0 }
+    }
+}
+
+impl Read for Scripted {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.sizes[self.reads % self.sizes.len()];
+        let n = size.min(buf.len()).min(self.data.len() - self.pos);
+        buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+        self.pos += n;
+        self.reads += 1;
+        Ok(n)
+    }
+}
+
+impl Write for Scripted {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for Scripted {
+    fn shutdown(&self, _how: Shutdown) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_read_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_write_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 两个 DATA（第二个不满一个分片）、END 和 ACK，按 `version` 编码
+fn sample_packets(version: u8, payload_len: usize) -> Vec<MessagePacket> {
+    let payload: Vec<u8> = (0..payload_len).map(|i| (i * 7 % 251) as u8).collect();
+    let mut packets = data_process::wrap_message_packets_with(payload, MAX_MESSAGE_BODY_SIZE);
+    packets.push(MessagePacket::new(MessageType::Ack, 0, 3, 0));
+    for packet in &mut packets {
+        packet.header.set_message_id(0x5a5a);
+        packet.set_version(version);
+    }
+    packets
+}
+
+fn encode(packets: &[MessagePacket]) -> Vec<u8> {
+    packets.iter().flat_map(|p| p.to_bytes()).collect()
+}
+
+/// 读出全部消息包并与原样比对，之后连接关闭应返回 EOF 错误
+fn assert_reads_back(stream: &mut FramedStream<Scripted>, packets: &[MessagePacket]) {
+    for expected in packets {
+        let packet = stream.read_packet().expect("读取消息包失败");
+        let summary = |p: &MessagePacket| {
+            (p.header.version, p.header.msg_type, p.header.message_id, p.header.total_size, p.header.chunk_index, p.header.chunk_count)
+        };
+        assert_eq!(summary(&packet), summary(expected));
+        assert_eq!(packet.body, expected.body);
+        packet.verify_checksum().expect("校验和错误");
+    }
+    let eof = stream.read_packet().err().expect("数据已读完");
+    assert!(matches!(&eof, ClientError::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof), "{:?}", eof);
+}
+
+#[test]
+fn one_byte_reads_reassemble_packets() {
+    for version in [PROTOCOL_VERSION_V1, PROTOCOL_VERSION_V2] {
+        let packets = sample_packets(version, MAX_MESSAGE_BODY_SIZE + 300);
+        assert_eq!(packets.len(), 4);
+        let data = encode(&packets);
+        let len = data.len();
+
+        let mut stream = FramedStream::new(Scripted::new(data, &[1]));
+        assert_reads_back(&mut stream, &packets);
+        assert_eq!(stream.get_mut().reads, len + 1, "v{} 每次只应读到 1 字节", version);
+    }
+}
+
+#[test]
+fn merged_read_yields_both_packets() {
+    for version in [PROTOCOL_VERSION_V1, PROTOCOL_VERSION_V2] {
+        // 一个 DATA、END 和 ACK 一共不到一次读取的缓冲区长度
+        let packets = sample_packets(version, 200);
+        assert_eq!(packets.len(), 3);
+        let data = encode(&packets);
+
+        let mut stream = FramedStream::new(Scripted::new(data, &[usize::MAX]));
+        let first = stream.read_packet().unwrap();
+        assert_eq!(first.header.msg_type, MessageType::Data);
+        assert_eq!(stream.get_mut().reads, 1);
+        // 其余消息包已在同一次读取中收到，不再读取连接
+        assert_eq!(stream.read_packet().unwrap().header.msg_type, MessageType::End);
+        assert_eq!(stream.read_packet().unwrap().header.msg_type, MessageType::Ack);
+        assert_eq!(stream.get_mut().reads, 1, "v{} 合并的消息包应只读取一次", version);
+    }
+}
+
+#[test]
+fn reads_split_across_packet_boundaries() {
+    for version in [PROTOCOL_VERSION_V1, PROTOCOL_VERSION_V2] {
+        let packets = sample_packets(version, 3 * MAX_MESSAGE_BODY_SIZE + 17);
+        // 读取边界落在消息头中间、消息体中间，以及跨越两个消息包
+        let mut stream = FramedStream::new(Scripted::new(encode(&packets), &[3, 700, 41, 1024]));
+        assert_reads_back(&mut stream, &packets);
+    }
+}
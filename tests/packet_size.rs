@@ -0,0 +1,172 @@
+// tests/packet_size.rs
+// START ACK 确认的消息包长度：接收上限只随确认增大，确认的长度小于默认分片长度或已有上限时保持不变，
+// 超出范围的确认按握手失败处理；单个传输 (`accept_start_ack`) 与多路复用的结果一致
+use std::os::unix::net::UnixStream;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use xbox_client::mux::Multiplexer;
+use xbox_client::protocol::consts::{MAX_MESSAGE_BODY_SIZE, MESSAGE_HEADER_SIZE_V2};
+use xbox_client::protocol::{control, utils as protocol_utils, FramedStream, MessagePacket, MessageType, StartAck, StartRequest, Window, WindowAck, WindowReceiver};
+use xbox_client::{constants, ClientError, CodecId};
+
+const OFFERED: u32 = 4096;
+
+fn request() -> StartRequest {
+    StartRequest { codec: CodecId::Zlib, window: 4, max_packet_size: OFFERED, ..StartRequest::default() }
+}
+
+fn ack_packet(max_packet_size: u32) -> MessagePacket {
+    let ack = StartAck { codec: CodecId::Zlib, window: 4, max_packet_size, ..StartAck::default() };
+    let mut packet = MessagePacket::new(MessageType::Ack, 0, 0, 0);
+    packet.header.set_message_id(1);
+    packet.set_body(ack.body_bytes());
+    packet
+}
+
+/// 对端发来一个消息体长度为 `len` 的 DATA，返回能否读出
+fn reads_body(stream: &mut FramedStream<UnixStream>, peer: &mut UnixStream, len: usize) -> bool {
+    use std::io::Write;
+    let mut packet = MessagePacket::new(MessageType::Data, len as u32, 0, 1);
+    packet.set_body(vec![0x5a; len]);
+    peer.write_all(&packet.to_bytes()).unwrap();
+    stream.read_packet().is_ok()
+}
+
+#[test]
+fn accept_start_ack_grows_but_never_shrinks_limit() {
+    let (local, _peer) = UnixStream::pair().unwrap();
+    let mut stream = FramedStream::new(local);
+    let request = request();
+    let accept = |stream: &mut FramedStream<UnixStream>, size: u32| {
+        protocol_utils::accept_start_ack(stream, &request, &ack_packet(size)).map(|ack| ack.max_packet_size)
+    };
+    assert_eq!(stream.max_body_size(), MAX_MESSAGE_BODY_SIZE);
+
+    // 未协商：使用默认分片长度
+    assert_eq!(accept(&mut stream, 0).unwrap(), 0);
+    assert_eq!(stream.max_body_size(), MAX_MESSAGE_BODY_SIZE);
+
+    // 确认的最小长度对应的消息体小于默认分片长度，接收上限保持默认值
+    assert_eq!(accept(&mut stream, 1024).unwrap(), 1024);
+    assert!(control::max_body_size(1024) < MAX_MESSAGE_BODY_SIZE);
+    assert_eq!(stream.max_body_size(), MAX_MESSAGE_BODY_SIZE);
+
+    // 增大
+    assert_eq!(accept(&mut stream, OFFERED).unwrap(), OFFERED);
+    assert_eq!(stream.max_body_size(), OFFERED as usize - MESSAGE_HEADER_SIZE_V2);
+
+    // 之后的传输确认了更小的长度：同一连接上可能仍有按大分片发送的传输，不缩小
+    assert_eq!(accept(&mut stream, 2048).unwrap(), 2048);
+    assert_eq!(stream.max_body_size(), OFFERED as usize - MESSAGE_HEADER_SIZE_V2);
+
+    // 小于最小长度、大于客户端提出的长度都是握手错误，接收上限不变
+    for size in [1023, OFFERED + 1] {
+        let result = accept(&mut stream, size);
+        assert!(matches!(result, Err(ClientError::Handshake(_))), "{}: {:?}", size, result);
+        assert_eq!(stream.max_body_size(), OFFERED as usize - MESSAGE_HEADER_SIZE_V2);
+    }
+
+    // 客户端未提出消息包长度时，服务端只能确认 0
+    let legacy = StartRequest { max_packet_size: 0, ..request.clone() };
+    let result = protocol_utils::accept_start_ack(&mut stream, &legacy, &ack_packet(1024));
+    assert!(matches!(result, Err(ClientError::Handshake(_))), "{:?}", result);
+}
+
+#[test]
+fn receive_limit_follows_accepted_size() {
+    let (local, mut peer) = UnixStream::pair().unwrap();
+    let mut stream = FramedStream::new(local);
+    stream.set_read_timeout(Some(Duration::from_secs(5)));
+    let request = request();
+
+    // 确认了较小的长度后，默认长度的分片仍然可以接收
+    protocol_utils::accept_start_ack(&mut stream, &request, &ack_packet(1024)).unwrap();
+    assert!(reads_body(&mut stream, &mut peer, MAX_MESSAGE_BODY_SIZE));
+
+    protocol_utils::accept_start_ack(&mut stream, &request, &ack_packet(OFFERED)).unwrap();
+    assert!(reads_body(&mut stream, &mut peer, OFFERED as usize - MESSAGE_HEADER_SIZE_V2));
+}
+
+/// 只处理一个传输的服务端：按 `accepted` 确认 START 的消息包长度，返回收到的全部消息包
+fn fake_server(stream: UnixStream, accepted: u32) -> JoinHandle<Vec<MessagePacket>> {
+    thread::spawn(move || {
+        let mut stream = FramedStream::new(stream);
+        stream.set_read_timeout(Some(Duration::from_secs(5)));
+        let start = stream.read_packet().unwrap();
+        let msg_id = start.header.message_id;
+        let request = StartRequest::from_packet(start.header.total_size, start.header.chunk_count, &start.body);
+        let ack = StartAck { codec: request.codec, window: request.window, max_packet_size: accepted, ..StartAck::default() };
+        protocol_utils::send_start_ack(&mut stream, msg_id, &ack).unwrap();
+
+        let mut receiver = WindowReceiver::new(Window::negotiated(request.window, 1), msg_id, 0);
+        let mut packets = Vec::new();
+        while let Ok(packet) = stream.read_packet() {
+            match packet.header.msg_type {
+                MessageType::Data => {
+                    receiver.receive(&mut stream, packet.clone()).unwrap();
+                }
+                MessageType::End => {
+                    let ack = WindowAck { cumulative: receiver.received(), ..WindowAck::default() }.to_packet(msg_id, stream.version());
+                    protocol_utils::send_data_message(&mut stream, &ack).unwrap();
+                }
+                _ => {}
+            }
+            packets.push(packet);
+        }
+        packets
+    })
+}
+
+/// 通过多路复用发送 `data`，服务端确认 `accepted`，返回传输结果和服务端收到的消息包
+fn mux_send(data: &[u8], accepted: u32) -> (Result<(), ClientError>, Vec<MessagePacket>) {
+    let (local, remote) = UnixStream::pair().unwrap();
+    let server = fake_server(remote, accepted);
+    let mut stream = FramedStream::new(local);
+    stream.set_read_timeout(Some(Duration::from_secs(5)));
+
+    let mut mux = Multiplexer::new(stream, 4, OFFERED);
+    mux.start(constants::SAVE_COMMAND, CodecId::Zlib, data).unwrap();
+    let (_, result) = mux.finish().unwrap().pop().unwrap();
+    (result, server.join().unwrap())
+}
+
+fn count(packets: &[MessagePacket], msg_type: MessageType) -> usize {
+    packets.iter().filter(|p| p.header.msg_type == msg_type).count()
+}
+
+#[test]
+fn multiplexer_uses_grown_packet_size() {
+    let data: Vec<u8> = (0..10000u32).map(|i| (i % 251) as u8).collect();
+    let (result, packets) = mux_send(&data, OFFERED);
+    result.unwrap();
+
+    // 按确认的长度分片，服务端调整接收上限后收到完整负载
+    let bodies: Vec<&[u8]> = packets.iter().filter(|p| p.header.msg_type == MessageType::Data).map(|p| p.body.as_slice()).collect();
+    assert_eq!(bodies.len(), 3);
+    assert_eq!(bodies[0].len(), OFFERED as usize - MESSAGE_HEADER_SIZE_V2);
+    assert_eq!(bodies.concat(), data);
+    assert_eq!(count(&packets, MessageType::End), 1);
+}
+
+#[test]
+fn multiplexer_rejects_shrunk_packet_size() {
+    // 分片已按提出的长度切好，服务端确认更小的长度时中止该传输
+    let (result, packets) = mux_send(&[0x42; 5000], 1024);
+    assert!(
+        matches!(result, Err(ClientError::PacketSizeRejected { offered: OFFERED, accepted: 1024 })),
+        "{:?}",
+        result
+    );
+    assert_eq!(count(&packets, MessageType::Data), 0);
+    assert_eq!(count(&packets, MessageType::Error), 1);
+}
+
+#[test]
+fn multiplexer_rejects_out_of_range_packet_size() {
+    for accepted in [512, OFFERED * 2] {
+        let (result, packets) = mux_send(&[0x42; 5000], accepted);
+        assert!(matches!(result, Err(ClientError::Handshake(_))), "{}: {:?}", accepted, result);
+        assert_eq!(count(&packets, MessageType::Data), 0);
+        assert_eq!(count(&packets, MessageType::Error), 1);
+    }
+}
@@ -0,0 +1,102 @@
+// tests/server.rs
+// 参考服务端：按旧版客户端的方式（v1 消息头，START / END 不携带总数）保存报告后能正常取回
+// 无法解析的报告使导出失败，而不是跳过
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use xbox_client::protocol::consts::PROTOCOL_VERSION_V1;
+use xbox_client::protocol::{control, utils as protocol_utils, FramedStream, MessagePacket, MessageType, StartRequest, Window, WindowSender};
+use xbox_client::server::{Server, Storage};
+use xbox_client::{constants, data_process, ClientConfig, Client, ClientError, CodecId, Endpoint, RetryPolicy};
+
+/// 在临时目录中启动参考服务端，返回 socket 路径
+fn start_server(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("xbox-server-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    let socket = dir.join("server.sock");
+    let storage = Storage::open(dir.join("storage")).unwrap();
+    let server = Server::bind(Endpoint::Unix(socket.clone()), storage).unwrap();
+    thread::spawn(move || server.run());
+    thread::sleep(Duration::from_millis(100));
+    socket
+}
+
+/// 按旧版客户端的方式保存 `compressed`：DATA 携带总数，START / END 均为 0/0，返回 END 的处理结果
+fn legacy_save(socket: &Path, compressed: &[u8], chunks: usize) -> Result<(), ClientError> {
+    let mut stream = FramedStream::with_version(UnixStream::connect(socket).unwrap(), PROTOCOL_VERSION_V1);
+    stream.set_read_timeout(Some(Duration::from_secs(5)));
+    let msg_id = 0x1234;
+    let request = StartRequest { total_size: 0, chunk_count: 0, ..StartRequest::default() };
+    let ack = protocol_utils::start_handshake(&mut stream, msg_id, constants::SAVE_COMMAND, &request)?;
+
+    let mut packets = data_process::wrap_message_packets_with(compressed.to_vec(), control::max_body_size(0));
+    packets.pop();
+    for packet in &mut packets {
+        packet.header.set_message_id(msg_id);
+    }
+    let mut sender = WindowSender::new(Window::negotiated(ack.window, 1), msg_id, 0);
+    for packet in packets.into_iter().take(chunks) {
+        sender.send(&mut stream, packet)?;
+    }
+    sender.finish(&mut stream)?;
+
+    let mut end = MessagePacket::new(MessageType::End, 0, 0, 0);
+    end.header.set_message_id(msg_id);
+    protocol_utils::send_data_message(&mut stream, &end)?;
+    protocol_utils::wait_for_ack(&mut stream, msg_id)?;
+    Ok(())
+}
+
+fn client(socket: &Path) -> Client {
+    let config = ClientConfig::builder()
+        .endpoint(Endpoint::Unix(socket.to_path_buf()))
+        .retry(RetryPolicy { max_attempts: 1, ..RetryPolicy::default() })
+        .build()
+        .unwrap();
+    Client::new(config)
+}
+
+/// 多个分片的 JSON 报告，zlib 压缩（旧版客户端的编码）
+fn legacy_report() -> (String, Vec<u8>, usize) {
+    let blob: String = (0..20000u32).map(|i| format!("{:08x}", i.wrapping_mul(2654435761))).collect();
+    let json = format!(r#"{{"servers":[],"blob":"{}"}}"#, blob);
+    let (compressed, _) = data_process::compress_with(CodecId::Zlib, json.as_bytes(), 6).unwrap();
+    let chunks = compressed.len().div_ceil(control::max_body_size(0));
+    assert!(chunks > 1, "报告应分成多个分片");
+    (json, compressed, chunks)
+}
+
+#[test]
+fn legacy_end_without_totals_uses_data_totals() {
+    let socket = start_server("legacy-end");
+    let (json, compressed, chunks) = legacy_report();
+
+    legacy_save(&socket, &compressed, chunks).expect("旧版客户端保存失败");
+
+    let dumped: Vec<serde_json::Value> = serde_json::from_slice(&client(&socket).dump_system().unwrap()).unwrap();
+    assert_eq!(dumped, vec![serde_json::from_str::<serde_json::Value>(&json).unwrap()]);
+}
+
+#[test]
+fn legacy_end_rejects_missing_chunks() {
+    let socket = start_server("legacy-short");
+    let (_, compressed, chunks) = legacy_report();
+
+    // 少发最后一个分片：DATA 中的总数与收到的不符
+    let result = legacy_save(&socket, &compressed, chunks - 1);
+    assert!(matches!(result, Err(ClientError::Remote(_))), "{:?}", result);
+    assert!(client(&socket).dump_system().unwrap().is_empty());
+}
+
+#[test]
+fn dump_fails_on_unparsable_report() {
+    let socket = start_server("bad-json");
+    let client = client(&socket);
+    client.send_system(r#"{"servers":[]}"#.to_string()).unwrap();
+    client.send_system("not json".to_string()).unwrap();
+
+    // 不返回缺少报告的结果
+    let result = client.dump_system();
+    assert!(matches!(result, Err(ClientError::Json(_))), "{:?}", result);
+}
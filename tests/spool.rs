@@ -0,0 +1,131 @@
+// tests/spool.rs
+// 本地 spool：记录格式、写入中途退出留下的不完整记录，以及总大小上限
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use xbox_client::constants;
+use xbox_client::spool::Spool;
+use xbox_client::ClientError;
+
+/// 每个测试使用独立的空目录
+fn spool_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("xbox-spool-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+/// 按文件名排序的分段文件
+fn segments(dir: &PathBuf) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|e| e == "seg"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn payloads(spool: &Spool) -> Vec<Vec<u8>> {
+    spool.list().unwrap().into_iter().map(|r| r.payload).collect()
+}
+
+#[test]
+fn record_layout_matches_format() {
+    let dir = spool_dir("layout");
+    let spool = Spool::open(&dir, 1024 * 1024, None).unwrap();
+    assert_eq!(spool.push(constants::SAVE_COMMAND, b"hello").unwrap(), 0);
+
+    let paths = segments(&dir);
+    assert_eq!(paths, vec![dir.join("00000000000000000000.seg")]);
+    let data = fs::read(&paths[0]).unwrap();
+
+    // magic "XBSR"(4) | seq(8) | created_ms(8) | command(1) | len(4) | crc32c(4) | payload(len)，大端
+    assert_eq!(data.len(), 29 + 5);
+    assert_eq!(&data[..4], b"XBSR");
+    assert_eq!(u64::from_be_bytes(data[4..12].try_into().unwrap()), 0);
+    let created_ms = u64::from_be_bytes(data[12..20].try_into().unwrap());
+    assert_eq!(data[20], constants::SAVE_COMMAND);
+    assert_eq!(u32::from_be_bytes(data[21..25].try_into().unwrap()), 5);
+    let checksum = crc32c::crc32c_append(crc32c::crc32c(&data[4..25]), b"hello");
+    assert_eq!(u32::from_be_bytes(data[25..29].try_into().unwrap()), checksum);
+    assert_eq!(&data[29..], b"hello");
+
+    let records = spool.list().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].created_ms, created_ms);
+    assert_eq!(records[0].command, constants::SAVE_COMMAND);
+}
+
+#[test]
+fn push_after_torn_tail_keeps_new_records_readable() {
+    let dir = spool_dir("torn-push");
+    let spool = Spool::open(&dir, 1024 * 1024, None).unwrap();
+    spool.push(constants::SAVE_COMMAND, b"first").unwrap();
+    spool.push(constants::SAVE_COMMAND, b"second").unwrap();
+
+    // 另一个进程写入中途退出：只写了记录头和部分负载
+    let segment = &segments(&dir)[0];
+    let valid_len = fs::metadata(segment).unwrap().len();
+    let mut torn = fs::read(segment).unwrap()[..29].to_vec();
+    torn[4..12].copy_from_slice(&2u64.to_be_bytes());
+    torn.extend_from_slice(b"par");
+    OpenOptions::new().append(true).open(segment).unwrap().write_all(&torn).unwrap();
+
+    assert_eq!(spool.push(constants::SAVE_COMMAND, b"third").unwrap(), 2);
+    assert_eq!(fs::metadata(segment).unwrap().len(), valid_len + 29 + 5);
+    assert_eq!(payloads(&spool), vec![b"first".to_vec(), b"second".to_vec(), b"third".to_vec()]);
+
+    // 补发后全部确认，分段被删除
+    let mut sent = Vec::new();
+    assert_eq!(spool.flush(|r| { sent.push(r.seq); Ok(()) }).unwrap(), 3);
+    assert_eq!(sent, vec![0, 1, 2]);
+    assert!(spool.is_empty().unwrap());
+    assert!(segments(&dir).is_empty());
+}
+
+#[test]
+fn open_truncates_corrupt_tail() {
+    let dir = spool_dir("torn-open");
+    {
+        let spool = Spool::open(&dir, 1024 * 1024, None).unwrap();
+        spool.push(constants::SAVE_COMMAND, b"first").unwrap();
+        spool.push(constants::SAVE_COMMAND, b"second").unwrap();
+    }
+
+    // 最后一条记录完整但负载损坏，只读取记录头无法发现
+    let segment = &segments(&dir)[0];
+    let mut data = fs::read(segment).unwrap();
+    let last = data.len() - 1;
+    data[last] ^= 0xff;
+    fs::write(segment, &data).unwrap();
+
+    let spool = Spool::open(&dir, 1024 * 1024, None).unwrap();
+    assert_eq!(fs::metadata(segment).unwrap().len(), 29 + 5);
+    assert_eq!(spool.push(constants::SAVE_COMMAND, b"third").unwrap(), 1);
+    assert_eq!(payloads(&spool), vec![b"first".to_vec(), b"third".to_vec()]);
+}
+
+#[test]
+fn max_bytes_caps_total_size() {
+    let dir = spool_dir("cap");
+    let max_bytes = 4096;
+    let spool = Spool::open(&dir, max_bytes, None).unwrap();
+
+    let payload = vec![b'x'; 1000];
+    for _ in 0..20 {
+        spool.push(constants::SAVE_COMMAND, &payload).unwrap();
+        let total: u64 = segments(&dir).iter().map(|p| fs::metadata(p).unwrap().len()).sum();
+        assert!(total <= max_bytes, "spool 总大小 {} 超过上限 {}", total, max_bytes);
+    }
+
+    // 丢弃的是最旧的记录，最新的记录保留
+    let seqs: Vec<u64> = spool.list().unwrap().iter().map(|r| r.seq).collect();
+    assert!(!seqs.is_empty() && seqs.len() < 20);
+    assert_eq!(*seqs.last().unwrap(), 19);
+    assert!(seqs.windows(2).all(|w| w[1] == w[0] + 1));
+
+    // 单条记录超过上限时拒绝写入
+    let result = spool.push(constants::SAVE_COMMAND, &vec![b'x'; max_bytes as usize]);
+    assert!(matches!(result, Err(ClientError::Io(_))), "{:?}", result);
+    assert_eq!(spool.list().unwrap().last().unwrap().seq, 19);
+}
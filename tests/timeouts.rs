@@ -0,0 +1,89 @@
+// tests/timeouts.rs
+// 各阶段超时：本地 Unix socket 上的服务端接受连接后不再回复，客户端应在配置的时限内返回超时错误
+use std::io::Read;
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use xbox_client::protocol::{utils as protocol_utils, FramedStream, StartAck, StartRequest};
+use xbox_client::{Client, ClientConfig, ClientConfigBuilder, ClientError, Endpoint, RetryPolicy};
+
+// 不应触发的阶段使用的超时，远大于被测阶段
+const LONG: Duration = Duration::from_secs(10);
+const SHORT: Duration = Duration::from_millis(300);
+// 超时返回允许的调度误差
+const SLACK: Duration = Duration::from_secs(2);
+
+/// 停滞的服务端：接受一个连接，`ack_start` 为 true 时先确认 START，之后只读取不回复，直到客户端关闭连接
+fn stalled_server(name: &str, ack_start: bool) -> (PathBuf, JoinHandle<()>) {
+    let path = std::env::temp_dir().join(format!("xbox-timeout-{}-{}.sock", std::process::id(), name));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).expect("绑定 Unix socket 失败");
+
+    let handle = thread::spawn(move || {
+        let (stream, _) = listener.accept().expect("接受连接失败");
+        let mut stream = FramedStream::new(stream);
+        if ack_start {
+            let start = stream.read_packet().expect("读取 START 失败");
+            let request = StartRequest::from_packet(start.header.total_size, start.header.chunk_count, &start.body);
+            let ack = StartAck {
+                resume_from: 0,
+                codec: request.codec,
+                window: request.window,
+                max_packet_size: request.max_packet_size,
+            };
+            protocol_utils::send_start_ack(&mut stream, start.header.message_id, &ack).expect("发送 START ACK 失败");
+        }
+        let mut buf = [0u8; 4096];
+        while matches!(stream.get_mut().read(&mut buf), Ok(n) if n > 0) {}
+    });
+    (path, handle)
+}
+
+/// 不重试、各阶段使用长超时的配置，由调用方缩短被测阶段
+fn config(path: &Path) -> ClientConfigBuilder {
+    ClientConfig::builder()
+        .endpoint(Endpoint::Unix(path.to_path_buf()))
+        .retry(RetryPolicy { max_attempts: 1, ..RetryPolicy::default() })
+        .connect_timeout(Some(LONG))
+        .handshake_timeout(Some(LONG))
+        .ack_timeout(Some(LONG))
+        .transfer_timeout(None)
+}
+
+/// 保存一份报告，断言在 [bound, bound + SLACK) 内返回超时错误
+fn assert_save_times_out(path: PathBuf, server: JoinHandle<()>, config: ClientConfigBuilder, bound: Duration) {
+    let client = Client::new(config.build().expect("配置无效"));
+    let started = Instant::now();
+    let result = client.send_system(r#"{"hostname":"timeout-test"}"#.to_string());
+    let elapsed = started.elapsed();
+
+    assert!(matches!(result, Err(ClientError::Timeout(_))), "期望超时错误，实际: {:?}", result);
+    assert!(elapsed >= bound, "{:?} 内就返回了，早于配置的 {:?}", elapsed, bound);
+    assert!(elapsed < bound + SLACK, "{:?} 才返回，超过配置的 {:?}", elapsed, bound);
+
+    // 客户端关闭连接后服务端线程结束
+    server.join().expect("服务端线程异常");
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn handshake_timeout_when_server_never_acks_start() {
+    let (path, server) = stalled_server("handshake", false);
+    let config = config(&path).handshake_timeout(Some(SHORT));
+    assert_save_times_out(path, server, config, SHORT);
+}
+
+#[test]
+fn ack_timeout_when_server_stops_acking_data() {
+    let (path, server) = stalled_server("ack", true);
+    let config = config(&path).ack_timeout(Some(SHORT));
+    assert_save_times_out(path, server, config, SHORT);
+}
+
+#[test]
+fn transfer_timeout_bounds_the_whole_transfer() {
+    let (path, server) = stalled_server("transfer", true);
+    let config = config(&path).transfer_timeout(Some(SHORT));
+    assert_save_times_out(path, server, config, SHORT);
+}
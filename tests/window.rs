@@ -0,0 +1,298 @@
+// tests/window.rs
+// 滑动窗口：分片乱序、重复、校验失败（相当于丢失）时，接收方按顺序还原负载，
+// 回复的累计 / 选择确认和重传请求正确，发送方据此移出已确认的分片并重传
+use std::collections::{BTreeSet, VecDeque};
+use std::io::{self, Read, Write};
+use std::net::Shutdown;
+use std::time::Duration;
+use xbox_client::protocol::consts::{ACK_FLAG_RETRANSMIT, MAX_RETRANSMITS};
+use xbox_client::protocol::{FramedStream, MessagePacket, MessageType, Window, WindowAck, WindowReceiver, WindowSender};
+use xbox_client::{data_process, ClientError, Transport};
+
+const MSG_ID: u32 = 0x77;
+const BODY_SIZE: usize = 10;
+
+/// 读取预先放入的字节、记录写出字节的连接
+#[derive(Default)]
+struct Wire {
+    input: VecDeque<u8>,
+    output: Vec<u8>,
+}
+
+impl Read for Wire {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl Write for Wire {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for Wire {
+    fn shutdown(&self, _how: Shutdown) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_read_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_write_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn stream() -> FramedStream<Wire> {
+    FramedStream::new(Wire::default())
+}
+
+/// 取出并解析 `stream` 写出的全部消息包
+fn written(stream: &mut FramedStream<Wire>) -> Vec<MessagePacket> {
+    let bytes = std::mem::take(&mut stream.get_mut().output);
+    let mut reader = FramedStream::new(Wire { input: bytes.into(), output: Vec::new() });
+    let mut packets = Vec::new();
+    while let Ok(packet) = reader.read_packet() {
+        packets.push(packet);
+    }
+    packets
+}
+
+fn written_acks(stream: &mut FramedStream<Wire>) -> Vec<WindowAck> {
+    written(stream)
+        .iter()
+        .inspect(|p| assert_eq!((p.header.msg_type, p.header.message_id), (MessageType::Ack, MSG_ID)))
+        .map(WindowAck::from_packet)
+        .collect()
+}
+
+fn payload(chunks: usize) -> Vec<u8> {
+    (0..chunks * BODY_SIZE).map(|i| (i * 13 % 251) as u8).collect()
+}
+
+/// 负载的 DATA 分片（不含 END）
+fn data_packets(payload: &[u8]) -> Vec<MessagePacket> {
+    let mut packets = data_process::wrap_message_packets_with(payload.to_vec(), BODY_SIZE);
+    packets.pop();
+    for packet in &mut packets {
+        packet.header.set_message_id(MSG_ID);
+    }
+    packets
+}
+
+/// 保留消息头中的校验和，改动消息体
+fn corrupt(packet: &MessagePacket) -> MessagePacket {
+    let mut packet = packet.clone();
+    packet.body[0] ^= 0xff;
+    packet
+}
+
+fn ack_packet(ack: &WindowAck) -> MessagePacket {
+    ack.to_packet(MSG_ID, stream().version())
+}
+
+fn indexes(packets: &[MessagePacket]) -> Vec<u32> {
+    packets.iter().map(|p| p.header.chunk_index).collect()
+}
+
+#[test]
+fn ack_round_trips_retransmit_flag() {
+    let ack = WindowAck { cumulative: 5, selective: vec![7, 9], retransmit: Some(6) };
+    let packet = ack_packet(&ack);
+    assert_eq!(packet.header.reserved, ACK_FLAG_RETRANSMIT);
+    assert_eq!((packet.header.chunk_index, packet.header.chunk_count), (5, 6));
+    assert_eq!(WindowAck::from_packet(&packet), ack);
+
+    // 未置标志时 chunk_count 不是重传编号
+    let plain = WindowAck { cumulative: 5, selective: vec![], retransmit: None };
+    assert_eq!(ack_packet(&plain).header.reserved, 0);
+    assert_eq!(WindowAck::from_packet(&ack_packet(&plain)), plain);
+}
+
+#[test]
+fn receiver_reorders_and_requests_retransmit() {
+    let payload = payload(6);
+    let packets = data_packets(&payload);
+    let window = Window::negotiated(8, 1);
+    assert_eq!(window.ack_every, 4);
+    let mut stream = stream();
+    let mut receiver = WindowReceiver::new(window, MSG_ID, 0);
+    let mut delivered: Vec<Vec<u8>> = Vec::new();
+    let mut receive = |stream: &mut FramedStream<Wire>, packet: MessagePacket| {
+        let bodies = receiver.receive(stream, packet).unwrap();
+        let count = bodies.len();
+        delivered.extend(bodies);
+        count
+    };
+
+    // 1、3 先到，暂存等待 0
+    assert_eq!(receive(&mut stream, packets[1].clone()), 0);
+    assert_eq!(receive(&mut stream, packets[3].clone()), 0);
+    assert!(written(&mut stream).is_empty());
+
+    // 0 校验失败：立即请求重传，并告知已收到的乱序分片
+    assert_eq!(receive(&mut stream, corrupt(&packets[0])), 0);
+    assert_eq!(written_acks(&mut stream), vec![WindowAck { cumulative: 0, selective: vec![1, 3], retransmit: Some(0) }]);
+
+    // 重传的 0 到达后交付 0、1；未达到确认间隔，不回复
+    assert_eq!(receive(&mut stream, packets[0].clone()), 2);
+    assert!(written(&mut stream).is_empty());
+
+    // 重复的分片直接忽略
+    assert_eq!(receive(&mut stream, packets[3].clone()), 0);
+    assert_eq!(receive(&mut stream, packets[0].clone()), 0);
+    assert!(written(&mut stream).is_empty());
+
+    // 2 补齐后交付 2、3，按顺序收到 4 个分片，回复累计确认
+    assert_eq!(receive(&mut stream, packets[2].clone()), 2);
+    assert_eq!(written_acks(&mut stream), vec![WindowAck { cumulative: 4, selective: vec![], retransmit: None }]);
+
+    // 最后两个分片乱序到达，全部收到后确认
+    assert_eq!(receive(&mut stream, packets[5].clone()), 0);
+    assert_eq!(receive(&mut stream, packets[4].clone()), 2);
+    assert_eq!(written_acks(&mut stream), vec![WindowAck { cumulative: 6, selective: vec![], retransmit: None }]);
+
+    assert_eq!(delivered.concat(), payload);
+    assert_eq!(receiver.received(), 6);
+}
+
+#[test]
+fn receiver_rejects_chunk_beyond_window() {
+    let packets = data_packets(&payload(12));
+    let mut stream = stream();
+    let mut receiver = WindowReceiver::new(Window::negotiated(4, 1), MSG_ID, 0);
+
+    let result = receiver.receive(&mut stream, packets[4].clone());
+    assert!(matches!(result, Err(ClientError::ProtocolViolation(_))), "{:?}", result);
+    assert!(receiver.receive(&mut stream, packets[3].clone()).unwrap().is_empty());
+}
+
+#[test]
+fn legacy_receiver_fails_on_checksum_error() {
+    let packets = data_packets(&payload(2));
+    let mut stream = stream();
+    let mut receiver = WindowReceiver::new(Window::negotiated(0, 2), MSG_ID, 0);
+
+    let result = receiver.receive(&mut stream, corrupt(&packets[0]));
+    assert!(matches!(result, Err(ClientError::Checksum(_))), "{:?}", result);
+    assert!(written(&mut stream).is_empty());
+}
+
+#[test]
+fn sender_handles_cumulative_selective_and_retransmit() {
+    let packets = data_packets(&payload(6));
+    let mut stream = stream();
+    let mut sender = WindowSender::new(Window::negotiated(4, 1), MSG_ID, 0);
+    for packet in &packets[..4] {
+        assert!(sender.can_send());
+        sender.send(&mut stream, packet.clone()).unwrap();
+    }
+    assert!(!sender.can_send());
+    assert_eq!(indexes(&written(&mut stream)), vec![0, 1, 2, 3]);
+
+    // 确认分片 0 和乱序收到的 3，请求重传 2：只重传 2
+    let ack = WindowAck { cumulative: 1, selective: vec![3], retransmit: Some(2) };
+    sender.handle_ack(&mut stream, &ack_packet(&ack)).unwrap();
+    assert_eq!(sender.in_flight(), 3);
+    let resent = written(&mut stream);
+    assert_eq!(indexes(&resent), vec![2]);
+    assert_eq!(resent[0].body, packets[2].body);
+
+    // 已被选择确认的分片不再重传
+    let ack = WindowAck { cumulative: 1, selective: vec![3], retransmit: Some(3) };
+    sender.handle_ack(&mut stream, &ack_packet(&ack)).unwrap();
+    assert!(written(&mut stream).is_empty());
+
+    // 确认位置回退或超出已发送的范围都是协议错误
+    for cumulative in [0, 5] {
+        let ack = WindowAck { cumulative, ..WindowAck::default() };
+        let result = sender.handle_ack(&mut stream, &ack_packet(&ack));
+        assert!(matches!(result, Err(ClientError::ProtocolViolation(_))), "{}: {:?}", cumulative, result);
+    }
+
+    // 窗口再次占满后，send 先读取 ACK 移出已确认的分片再发送
+    sender.send(&mut stream, packets[4].clone()).unwrap();
+    assert!(!sender.can_send());
+    queue(&mut stream, &WindowAck { cumulative: 4, ..WindowAck::default() });
+    sender.send(&mut stream, packets[5].clone()).unwrap();
+    assert_eq!(sender.in_flight(), 2);
+    assert_eq!(indexes(&written(&mut stream)), vec![4, 5]);
+
+    queue(&mut stream, &WindowAck { cumulative: 6, ..WindowAck::default() });
+    sender.finish(&mut stream).unwrap();
+    assert_eq!(sender.in_flight(), 0);
+}
+
+#[test]
+fn sender_gives_up_after_max_retransmits() {
+    let packets = data_packets(&payload(1));
+    let mut stream = stream();
+    let mut sender = WindowSender::new(Window::negotiated(4, 1), MSG_ID, 0);
+    sender.send(&mut stream, packets[0].clone()).unwrap();
+    written(&mut stream);
+
+    let ack = ack_packet(&WindowAck { cumulative: 0, selective: vec![], retransmit: Some(0) });
+    for _ in 0..MAX_RETRANSMITS {
+        sender.handle_ack(&mut stream, &ack).unwrap();
+    }
+    assert_eq!(indexes(&written(&mut stream)), vec![0; MAX_RETRANSMITS as usize]);
+    let result = sender.handle_ack(&mut stream, &ack);
+    assert!(matches!(result, Err(ClientError::Checksum(_))), "{:?}", result);
+}
+
+/// 发送方与接收方之间的线路：每一轮发出的分片倒序到达，`damaged` 中的分片第一次发送时损坏
+#[test]
+fn lossy_reordering_link_reassembles_payload() {
+    let payload = payload(20);
+    let packets = data_packets(&payload);
+    let window = Window::negotiated(8, 1);
+    let mut sender_stream = stream();
+    let mut receiver_stream = stream();
+    let mut sender = WindowSender::new(window, MSG_ID, 0);
+    let mut receiver = WindowReceiver::new(window, MSG_ID, 0);
+
+    let mut damaged: BTreeSet<u32> = [5, 13, 19].into();
+    let mut retransmit_requests = BTreeSet::new();
+    let mut delivered = Vec::new();
+    let mut next = 0;
+    for round in 0.. {
+        assert!(round < 100, "传输没有进展");
+        while next < packets.len() && sender.can_send() {
+            sender.send(&mut sender_stream, packets[next].clone()).unwrap();
+            next += 1;
+        }
+
+        for packet in written(&mut sender_stream).into_iter().rev() {
+            let index = packet.header.chunk_index;
+            let packet = if damaged.remove(&index) { corrupt(&packet) } else { packet };
+            delivered.extend(receiver.receive(&mut receiver_stream, packet).unwrap());
+        }
+
+        for ack in written(&mut receiver_stream) {
+            retransmit_requests.extend(WindowAck::from_packet(&ack).retransmit);
+            sender.handle_ack(&mut sender_stream, &ack).unwrap();
+        }
+        // 重传的分片留在线路上，下一轮随新分片一起到达
+
+        if next == packets.len() && sender.in_flight() == 0 {
+            break;
+        }
+    }
+
+    assert_eq!(retransmit_requests, [5, 13, 19].into());
+    assert_eq!(delivered.concat(), payload);
+    assert_eq!(receiver.received(), packets.len() as u32);
+}
+
+/// 放入一个待 `stream` 读取的 ACK
+fn queue(stream: &mut FramedStream<Wire>, ack: &WindowAck) {
+    let bytes = ack_packet(ack).to_bytes();
+    stream.get_mut().input.extend(bytes);
+}